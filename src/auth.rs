@@ -0,0 +1,612 @@
+use std::{env, fmt, path::PathBuf, process::Command, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+
+use crate::{
+    client::HttpClient,
+    config::GConfig,
+    models::google::{
+        AuthorizedUserCredentials, CredentialsFile, ErrorResponse, GenerateAccessTokenRequest,
+        GenerateAccessTokenResponse, JwtClaims, ServiceAccountKey, TokenErrorResponse,
+        TokenResponse, REDACTED,
+    },
+    warn, GError, GResult,
+};
+
+pub const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+pub const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+pub const DEFAULT_METADATA_HOST: &str = "metadata.google.internal";
+pub const DEFAULT_IAM_CREDENTIALS_BASE_URL: &str = "https://iamcredentials.googleapis.com/v1";
+
+/// Keeps the metadata server lookup short outside of Compute Engine and GKE. The metadata server
+/// is link-local, so it gets its own client which never goes through the configured proxy.
+const METADATA_TIMEOUT_SECS: u64 = 3;
+
+/// Tokens are refreshed this long before they expire
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AccessToken {
+    pub token: String,
+    /// Unknown for tokens printed by gcloud, which are then only cached in memory
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("AccessToken")
+            .field("token", &REDACTED)
+            .field("expires_at", &self.expires_at)
+            .finish();
+    }
+}
+
+impl AccessToken {
+    pub fn is_expiring(&self) -> bool {
+        return match self.expires_at {
+            Some(expires_at) => expires_at - Duration::seconds(EXPIRY_MARGIN_SECS) <= Utc::now(),
+            None => false,
+        };
+    }
+}
+
+/// Access token together with the credentials it was issued for, `Debug` redacts it through
+/// `AccessToken`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedToken {
+    key: String,
+    access_token: AccessToken,
+}
+
+impl CachedToken {
+    fn path() -> GResult<PathBuf> {
+        let path = dirs::data_dir()
+            .ok_or_else(|| GError::NotFound("Unable to get data directory".into()))?
+            .join("gsqueal/token")
+            .with_extension("json");
+
+        return Ok(path);
+    }
+    async fn load(key: &str) -> GResult<Option<Self>> {
+        let token_path = Self::path()?;
+        if !token_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&token_path).await?;
+        // A corrupted or stale cache is simply discarded and replaced by a fresh token
+        let cached_token = serde_json::from_str::<Self>(&content)
+            .ok()
+            .filter(|cached_token| cached_token.key == key)
+            .filter(|cached_token| {
+                cached_token.access_token.expires_at.is_some()
+                    && !cached_token.access_token.is_expiring()
+            });
+
+        return Ok(cached_token);
+    }
+    async fn save(&self) -> GResult<()> {
+        let token_path = Self::path()?;
+
+        if let Some(parent) = token_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let serialized_data = serde_json::to_string_pretty(self)
+            .map_err(|err| GError::Unknown(format!("Failed to serialize token cache: {}", err)))?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(&token_path).await?;
+        // The mode only applies to new files, so tighten an existing one as well
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))
+            .await?;
+        file.write_all(serialized_data.as_bytes()).await?;
+
+        return Ok(());
+    }
+    async fn remove() -> GResult<()> {
+        let token_path = Self::path()?;
+        if token_path.exists() {
+            fs::remove_file(token_path).await?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum CredentialsProvider {
+    /// Service account key or `authorized_user` credentials file
+    File(PathBuf),
+    /// `gcloud auth print-access-token`
+    Gcloud,
+    /// Attached service account of a Compute Engine VM or GKE workload, `host` may include a port
+    Metadata { host: String },
+}
+
+impl CredentialsProvider {
+    pub fn name(&self) -> String {
+        return match self {
+            Self::File(path) => format!("file:{}", path.display()),
+            Self::Gcloud => "gcloud".into(),
+            Self::Metadata { host } => format!("metadata:{}", host),
+        };
+    }
+    /// Providers to try in order, an explicitly configured credentials file is used exclusively
+    pub fn chain(config: &GConfig) -> Vec<Self> {
+        if let Some(path) = env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+            return vec![Self::File(PathBuf::from(path))];
+        }
+        if let Some(path) = &config.auth.credentials_file {
+            return vec![Self::File(path.clone())];
+        }
+
+        let mut chain = vec![Self::Gcloud];
+        if let Some(path) = well_known_credentials_file().filter(|path| path.exists()) {
+            chain.push(Self::File(path));
+        }
+        let host = env::var("GCE_METADATA_HOST")
+            .ok()
+            .or_else(|| config.auth.metadata_host.clone())
+            .unwrap_or_else(|| DEFAULT_METADATA_HOST.into());
+        chain.push(Self::Metadata { host });
+
+        return chain;
+    }
+    pub async fn access_token(&self, http: &HttpClient) -> GResult<AccessToken> {
+        return match self {
+            Self::File(path) => {
+                let content = fs::read_to_string(path).await.map_err(|err| {
+                    GError::Auth(format!(
+                        "Unable to read credentials file `{}`: {}",
+                        path.display(),
+                        err
+                    ))
+                })?;
+                let credentials: CredentialsFile =
+                    serde_json::from_str(&content).map_err(|err| {
+                        GError::Auth(format!(
+                            "Invalid credentials file `{}`: {}",
+                            path.display(),
+                            err
+                        ))
+                    })?;
+
+                match credentials {
+                    CredentialsFile::ServiceAccount(key) => service_account_token(http, &key).await,
+                    CredentialsFile::AuthorizedUser(credentials) => {
+                        authorized_user_token(http, &credentials).await
+                    }
+                }
+            }
+            Self::Gcloud => gcloud_token(),
+            Self::Metadata { host } => metadata_token(host).await,
+        };
+    }
+}
+
+/// Credentials shared by the API clients, tokens are cached until they're about to expire
+#[derive(Clone)]
+pub struct Credentials {
+    providers: Vec<CredentialsProvider>,
+    impersonate_service_account: Option<String>,
+    iam_credentials_base_url: String,
+    persist_token_cache: bool,
+    http: HttpClient,
+    cache: Arc<Mutex<Option<CachedToken>>>,
+}
+
+/// Leaves out the cached token, `ApiClient` and the service clients print credentials through this
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("Credentials")
+            .field("providers", &self.providers)
+            .field(
+                "impersonate_service_account",
+                &self.impersonate_service_account,
+            )
+            .field("iam_credentials_base_url", &self.iam_credentials_base_url)
+            .field("persist_token_cache", &self.persist_token_cache)
+            .field("http", &self.http)
+            .field("cache", &REDACTED)
+            .finish();
+    }
+}
+
+impl Credentials {
+    pub fn new(providers: Vec<CredentialsProvider>) -> Self {
+        return Self {
+            providers,
+            impersonate_service_account: None,
+            iam_credentials_base_url: DEFAULT_IAM_CREDENTIALS_BASE_URL.into(),
+            persist_token_cache: false,
+            http: HttpClient::default(),
+            cache: Arc::new(Mutex::new(None)),
+        };
+    }
+    /// Default provider chain, impersonation and token cache settings from the config
+    pub fn from_config(config: &GConfig) -> Self {
+        return Self::new(CredentialsProvider::chain(config))
+            .impersonate(config.auth.impersonate_service_account.clone())
+            .persist_token_cache(config.auth.persist_token_cache);
+    }
+    /// Acts as `service_account` through the IAM Credentials API, or as the base credentials if
+    /// `None`
+    pub fn impersonate(mut self, service_account: Option<String>) -> Self {
+        self.impersonate_service_account = service_account;
+        self.cache = Arc::new(Mutex::new(None));
+        return self;
+    }
+    pub fn with_iam_credentials_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.iam_credentials_base_url = base_url.into().trim_end_matches('/').to_string();
+        return self;
+    }
+    /// Persists tokens next to the store so they're reused across invocations
+    pub fn persist_token_cache(mut self, persist_token_cache: bool) -> Self {
+        self.persist_token_cache = persist_token_cache;
+        return self;
+    }
+    /// Sends token requests through `http`, `ApiClient` passes its own
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        return self;
+    }
+    pub fn impersonated_service_account(&self) -> Option<&str> {
+        return self.impersonate_service_account.as_deref();
+    }
+    /// Returns a cached access token, only fetching a new one when missing or about to expire
+    pub async fn access_token(&self) -> GResult<AccessToken> {
+        let mut cache = self.cache.lock().await;
+        if let Some(cached_token) = cache.as_ref()
+            && !cached_token.access_token.is_expiring()
+        {
+            return Ok(cached_token.access_token.clone());
+        }
+
+        let key = self.cache_key();
+        if self.persist_token_cache
+            && let Some(cached_token) = CachedToken::load(&key).await?
+        {
+            let access_token = cached_token.access_token.clone();
+            *cache = Some(cached_token);
+            return Ok(access_token);
+        }
+
+        let mut access_token = fetch_access_token(&self.http, &self.providers).await?;
+        if let Some(service_account) = &self.impersonate_service_account {
+            access_token = impersonated_token(
+                &self.http,
+                &self.iam_credentials_base_url,
+                &access_token,
+                service_account,
+            )
+            .await?;
+        }
+        let cached_token = CachedToken {
+            key,
+            access_token: access_token.clone(),
+        };
+        if self.persist_token_cache && access_token.expires_at.is_some() {
+            cached_token.save().await?;
+        }
+        *cache = Some(cached_token);
+
+        return Ok(access_token);
+    }
+    /// Drops the cached access token, e.g. after the API rejected it with a 401
+    pub async fn invalidate(&self) -> GResult<()> {
+        self.cache.lock().await.take();
+        if self.persist_token_cache {
+            CachedToken::remove().await?;
+        }
+
+        return Ok(());
+    }
+    fn cache_key(&self) -> String {
+        let mut key = self
+            .providers
+            .iter()
+            .map(CredentialsProvider::name)
+            .collect::<Vec<_>>()
+            .join(",");
+        if let Some(service_account) = &self.impersonate_service_account {
+            key.push_str(&format!(",impersonate:{}", service_account));
+        }
+
+        return key;
+    }
+}
+
+/// Returns the first provider's token, or its error if every provider failed since the later
+/// ones are fallbacks which are expected to be missing most of the time
+async fn fetch_access_token(
+    http: &HttpClient,
+    providers: &[CredentialsProvider],
+) -> GResult<AccessToken> {
+    let mut first_error = None;
+
+    for provider in providers {
+        match provider.access_token(http).await {
+            Ok(access_token) => return Ok(access_token),
+            Err(err) => {
+                if first_error.is_some() {
+                    warn!("Credentials provider `{}` failed: {}", provider.name(), err);
+                } else {
+                    first_error = Some(err);
+                }
+            }
+        }
+    }
+
+    return Err(first_error.unwrap_or_else(|| GError::Auth("No credentials found".into())));
+}
+
+fn well_known_credentials_file() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return env::var_os("APPDATA").map(|appdata| {
+            PathBuf::from(appdata).join("gcloud/application_default_credentials.json")
+        });
+    }
+
+    return dirs::home_dir()
+        .map(|home| home.join(".config/gcloud/application_default_credentials.json"));
+}
+
+fn gcloud_token() -> GResult<AccessToken> {
+    let gcloud_auth_cmd = Command::new("gcloud")
+        .arg("auth")
+        .arg("print-access-token")
+        .output()
+        .map_err(|_| {
+            GError::Auth(
+                "Unable to run gcloud, please make sure gcloud CLI is installed and you're logged in."
+                    .into(),
+            )
+        })?;
+    if !gcloud_auth_cmd.status.success() {
+        return Err(GError::Auth(
+            String::from_utf8_lossy(&gcloud_auth_cmd.stderr)
+                .trim()
+                .to_string(),
+        ));
+    }
+    let access_token = String::from_utf8_lossy(&gcloud_auth_cmd.stdout)
+        .trim()
+        .to_string();
+    if access_token.is_empty() {
+        return Err(GError::Auth(
+            "gcloud returned an empty access token, please make sure you're logged in.".into(),
+        ));
+    }
+
+    return Ok(AccessToken {
+        token: access_token,
+        expires_at: None,
+    });
+}
+
+async fn service_account_token(http: &HttpClient, key: &ServiceAccountKey) -> GResult<AccessToken> {
+    let token_uri = key.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+    let issued_at = Utc::now();
+    let claims = JwtClaims {
+        iss: key.client_email.clone(),
+        scope: CLOUD_PLATFORM_SCOPE.into(),
+        aud: token_uri.into(),
+        iat: issued_at.timestamp(),
+        exp: (issued_at + Duration::hours(1)).timestamp(),
+    };
+
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = key.private_key_id.clone();
+    let encoding_key = EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+        .map_err(|err| GError::Auth(format!("Invalid service account private key: {}", err)))?;
+    let assertion = jsonwebtoken::encode(&header, &claims, &encoding_key)
+        .map_err(|err| GError::Auth(format!("Unable to sign JWT: {}", err)))?;
+
+    return exchange_token(
+        http,
+        token_uri,
+        &[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", &assertion),
+        ],
+    )
+    .await;
+}
+
+async fn authorized_user_token(
+    http: &HttpClient,
+    credentials: &AuthorizedUserCredentials,
+) -> GResult<AccessToken> {
+    let token_uri = credentials
+        .token_uri
+        .as_deref()
+        .unwrap_or(DEFAULT_TOKEN_URI);
+
+    return exchange_token(
+        http,
+        token_uri,
+        &[
+            ("grant_type", "refresh_token"),
+            ("client_id", &credentials.client_id),
+            ("client_secret", &credentials.client_secret),
+            ("refresh_token", &credentials.refresh_token),
+        ],
+    )
+    .await;
+}
+
+async fn exchange_token(
+    http: &HttpClient,
+    token_uri: &str,
+    form: &[(&str, &str)],
+) -> GResult<AccessToken> {
+    let (status, body) = http
+        .send_with_retry(http.client().post(token_uri).form(form))
+        .await?;
+
+    if !status.is_success() {
+        let message = match serde_json::from_str::<TokenErrorResponse>(&body) {
+            Ok(TokenErrorResponse {
+                error,
+                error_description: Some(description),
+            }) => format!("{}: {}", error, description),
+            Ok(TokenErrorResponse { error, .. }) => error,
+            Err(_) => body,
+        };
+        return Err(GError::Auth(format!(
+            "Token exchange failed with HTTP status {}: {}",
+            status.as_u16(),
+            message
+        )));
+    }
+
+    let TokenResponse {
+        access_token,
+        expires_in,
+    } = serde_json::from_str(&body).map_err(|source| GError::Json { source, body })?;
+
+    return Ok(AccessToken {
+        token: access_token,
+        expires_at: expires_in.map(|expires_in| Utc::now() + Duration::seconds(expires_in)),
+    });
+}
+
+async fn metadata_token(host: &str) -> GResult<AccessToken> {
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(METADATA_TIMEOUT_SECS))
+        .build()?;
+    let response = client
+        .get(format!(
+            "http://{}/computeMetadata/v1/instance/service-accounts/default/token",
+            host
+        ))
+        .header("Metadata-Flavor", "Google")
+        .send()
+        .await
+        .map_err(|err| {
+            GError::Auth(format!(
+                "Unable to reach metadata server `{}`: {}",
+                host, err
+            ))
+        })?;
+    let status = response.status();
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        return Err(GError::Auth(format!(
+            "Metadata server `{}` responded with HTTP status {}: {}",
+            host,
+            status.as_u16(),
+            body.trim()
+        )));
+    }
+
+    let TokenResponse {
+        access_token,
+        expires_in,
+    } = serde_json::from_str(&body).map_err(|source| GError::Json { source, body })?;
+
+    return Ok(AccessToken {
+        token: access_token,
+        expires_at: expires_in.map(|expires_in| Utc::now() + Duration::seconds(expires_in)),
+    });
+}
+
+/// Exchanges the base credentials for a short-lived token of `service_account` through the IAM
+/// Credentials API, which requires the Service Account Token Creator role on it
+async fn impersonated_token(
+    http: &HttpClient,
+    base_url: &str,
+    access_token: &AccessToken,
+    service_account: &str,
+) -> GResult<AccessToken> {
+    let request = GenerateAccessTokenRequest {
+        scope: vec![CLOUD_PLATFORM_SCOPE.into()],
+        lifetime: "3600s".into(),
+    };
+
+    let (status, body) = http
+        .send_with_retry(
+            http.client()
+                .post(format!(
+                    "{}/projects/-/serviceAccounts/{}:generateAccessToken",
+                    base_url, service_account
+                ))
+                .bearer_auth(&access_token.token)
+                .json(&request),
+        )
+        .await?;
+
+    if !status.is_success() {
+        let message = match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(ErrorResponse { error }) => error.message,
+            Err(_) => body,
+        };
+        return Err(GError::Auth(format!(
+            "Unable to impersonate `{}`: {}\n  hint: Make sure your account has the Service \
+             Account Token Creator role on the service account",
+            service_account, message
+        )));
+    }
+
+    let GenerateAccessTokenResponse {
+        access_token,
+        expire_time,
+    } = serde_json::from_str(&body).map_err(|source| GError::Json { source, body })?;
+
+    return Ok(AccessToken {
+        token: access_token,
+        expires_at: Some(expire_time),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn debug_output_redacts_secrets() {
+        let credentials = Credentials::new(vec![CredentialsProvider::Gcloud]);
+        *credentials.cache.lock().await = Some(CachedToken {
+            key: credentials.cache_key(),
+            access_token: AccessToken {
+                token: "secret-access-token".into(),
+                expires_at: None,
+            },
+        });
+        let key = ServiceAccountKey {
+            client_email: "deployer@my-project.iam.gserviceaccount.com".into(),
+            private_key: "secret-private-key".into(),
+            private_key_id: None,
+            token_uri: None,
+        };
+        let authorized_user = AuthorizedUserCredentials {
+            client_id: "client-id".into(),
+            client_secret: "secret-client-secret".into(),
+            refresh_token: "secret-refresh-token".into(),
+            token_uri: None,
+        };
+
+        let output = format!(
+            "{:?} {:?} {:?} {:?}",
+            credentials,
+            credentials.cache.lock().await,
+            key,
+            authorized_user
+        );
+        assert!(!output.contains("secret-"), "{}", output);
+        assert!(output.contains("deployer@my-project.iam.gserviceaccount.com"));
+        assert!(output.contains("client-id"));
+    }
+}
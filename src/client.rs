@@ -0,0 +1,772 @@
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{self, HeaderValue},
+    Client, Method, Proxy, RequestBuilder, Response, StatusCode,
+};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use tokio::time::sleep;
+
+use crate::{
+    auth::Credentials,
+    config::GConfig,
+    models::{
+        google::{
+            AuthorizedNetwork, ErrorResponse, Instance, InstancesResponse, Operation,
+            OperationStatus, OperationsResponse, Project, ProjectsResponse,
+        },
+        InstanceUpdateDto, IpConfigurationUpdateDto, NetworkUpdateDto, SettingsUpdateDto,
+    },
+    utils::{self, validate_network_value},
+    warn, GError, GResult,
+};
+
+pub const DEFAULT_USER_AGENT: &str = concat!("gsqueal/", env!("CARGO_PKG_VERSION"));
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(32);
+const MAX_CONFLICT_ATTEMPTS: u32 = 3;
+const OPERATION_POLL_BASE_DELAY: Duration = Duration::from_secs(1);
+const OPERATION_POLL_MAX_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct ClientOptions {
+    pub user_agent: String,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Proxy URL for every request, the `HTTPS_PROXY` environment variables apply otherwise
+    pub proxy: Option<String>,
+    /// Attempts per request including the first one, transient failures are retried
+    pub max_attempts: u32,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        return Self {
+            user_agent: DEFAULT_USER_AGENT.into(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+            proxy: None,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        };
+    }
+}
+
+impl ClientOptions {
+    pub fn from_config(config: &GConfig) -> Self {
+        let defaults = Self::default();
+
+        return Self {
+            user_agent: config
+                .http
+                .user_agent
+                .clone()
+                .unwrap_or(defaults.user_agent),
+            timeout: config
+                .http
+                .timeout_secs
+                .map_or(defaults.timeout, Duration::from_secs),
+            connect_timeout: config
+                .http
+                .connect_timeout_secs
+                .map_or(defaults.connect_timeout, Duration::from_secs),
+            proxy: config.http.proxy.clone(),
+            max_attempts: config.http.max_attempts.unwrap_or(defaults.max_attempts),
+        };
+    }
+}
+
+/// Base URL overrides, `None` uses the service's default endpoint
+#[derive(Clone, Debug, Default)]
+pub struct Endpoints {
+    pub sqladmin: Option<String>,
+    pub cloudresourcemanager: Option<String>,
+    pub iamcredentials: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl Endpoints {
+    /// Applies `SERVICE=URL` overrides over the `GSQUEAL_<SERVICE>_ENDPOINT` environment variables
+    /// and the config
+    pub fn resolve(config: &GConfig, overrides: &[String]) -> GResult<Self> {
+        let from_env = |service: &str| {
+            env::var(format!("GSQUEAL_{}_ENDPOINT", service.to_uppercase()))
+                .ok()
+                .filter(|url| !url.is_empty())
+        };
+        let mut endpoints = Self {
+            sqladmin: from_env("sqladmin").or_else(|| config.endpoints.sqladmin.clone()),
+            cloudresourcemanager: from_env("cloudresourcemanager")
+                .or_else(|| config.endpoints.cloudresourcemanager.clone()),
+            iamcredentials: from_env("iamcredentials")
+                .or_else(|| config.endpoints.iamcredentials.clone()),
+            ip: from_env("ip").or_else(|| config.endpoints.ip.clone()),
+        };
+
+        for endpoint in overrides {
+            let (service, url) = endpoint.split_once('=').ok_or_else(|| {
+                GError::InvalidInput(format!(
+                    "Invalid API endpoint `{}`, expected `SERVICE=URL`",
+                    endpoint
+                ))
+            })?;
+            let url = Some(url.to_string());
+            match service {
+                "sqladmin" => endpoints.sqladmin = url,
+                "cloudresourcemanager" => endpoints.cloudresourcemanager = url,
+                "iamcredentials" => endpoints.iamcredentials = url,
+                "ip" => endpoints.ip = url,
+                _ => {
+                    return Err(GError::InvalidInput(format!(
+                        "Unknown service `{}`, expected `sqladmin`, `cloudresourcemanager`, \
+                         `iamcredentials` or `ip`",
+                        service
+                    )));
+                }
+            }
+        }
+
+        return Ok(endpoints);
+    }
+}
+
+/// Connection pool built from the client options, shared by the API clients, token exchanges and
+/// IP discovery so they all use the configured proxy, user agent, timeouts and attempts
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    client: Client,
+    max_attempts: u32,
+}
+
+impl HttpClient {
+    pub fn new(options: &ClientOptions) -> GResult<Self> {
+        let mut builder = Client::builder()
+            .user_agent(&options.user_agent)
+            .timeout(options.timeout)
+            .connect_timeout(options.connect_timeout);
+        if let Some(proxy) = &options.proxy {
+            let proxy = Proxy::all(proxy).map_err(|err| {
+                GError::InvalidInput(format!("Invalid proxy `{}`: {}", proxy, err))
+            })?;
+            builder = builder.proxy(proxy);
+        }
+
+        return Ok(Self {
+            client: builder.build()?,
+            max_attempts: options.max_attempts.max(1),
+        });
+    }
+    pub fn client(&self) -> &Client {
+        return &self.client;
+    }
+    /// Sends a request without credentials handling, retrying rate limits and failed connections
+    /// with exponential backoff. The last response is returned as is, whatever its status.
+    pub async fn send_with_retry(&self, request: RequestBuilder) -> GResult<(StatusCode, String)> {
+        let request = request.build()?;
+        let mut attempt = 1;
+
+        loop {
+            let attempt_request = request
+                .try_clone()
+                .ok_or_else(|| GError::Unknown("Unable to clone request for retrying".into()))?;
+
+            let (err, retry_after) = match self.client.execute(attempt_request).await {
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = retry_after(&response);
+                    let body = response.text().await?;
+                    // Only rate limited requests are known to be unprocessed, see `is_retryable`
+                    if status != StatusCode::TOO_MANY_REQUESTS || attempt >= self.max_attempts {
+                        return Ok((status, body));
+                    }
+
+                    let err = GError::HttpStatus {
+                        status: status.as_u16(),
+                        body,
+                    };
+                    (err, retry_after)
+                }
+                Err(err) => {
+                    let err = GError::Http(err);
+                    if attempt >= self.max_attempts || !is_retryable(&err, false) {
+                        return Err(err);
+                    }
+                    (err, None)
+                }
+            };
+
+            let delay = retry_after.unwrap_or_else(|| backoff_delay(attempt));
+            warn!(
+                "{} {} failed, retrying in {:.1}s (attempt {}/{}): {}",
+                request.method(),
+                request.url().path(),
+                delay.as_secs_f64(),
+                attempt + 1,
+                self.max_attempts,
+                err
+            );
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl Default for HttpClient {
+    /// Default options, or reqwest's defaults in the unlikely case the TLS backend fails with them
+    fn default() -> Self {
+        return Self::new(&ClientOptions::default()).unwrap_or_else(|_| Self {
+            client: Client::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        });
+    }
+}
+
+/// Connection pool and credentials shared by the service clients, cheap to clone
+#[derive(Clone, Debug)]
+pub struct ApiClient {
+    http: HttpClient,
+    credentials: Credentials,
+}
+
+impl ApiClient {
+    pub fn new(options: ClientOptions, credentials: Credentials) -> GResult<Self> {
+        let http = HttpClient::new(&options)?;
+
+        return Ok(Self {
+            credentials: credentials.with_http_client(http.clone()),
+            http,
+        });
+    }
+    /// Client options and credentials from the config
+    pub async fn from_config() -> GResult<Self> {
+        let config = GConfig::get().await?;
+
+        return Self::new(
+            ClientOptions::from_config(&config),
+            Credentials::from_config(&config),
+        );
+    }
+    pub fn credentials(&self) -> &Credentials {
+        return &self.credentials;
+    }
+    pub fn http(&self) -> &HttpClient {
+        return &self.http;
+    }
+    /// Sends an authorized request, refreshing the access token once after a 401 and retrying
+    /// transient failures with exponential backoff, GETs on any transient error and other
+    /// methods only when the request was never processed
+    async fn send_json<T: DeserializeOwned>(&self, request: RequestBuilder) -> GResult<T> {
+        let request = request.build()?;
+        let idempotent = request.method() == Method::GET;
+        let mut attempt = 1;
+        let mut refreshed_token = false;
+
+        loop {
+            let access_token = self.credentials.access_token().await?;
+            let mut attempt_request = request
+                .try_clone()
+                .ok_or_else(|| GError::Unknown("Unable to clone request for retrying".into()))?;
+            let authorization = HeaderValue::from_str(&format!("Bearer {}", access_token.token))
+                .map_err(|_| GError::Auth("Access token contains invalid characters".into()))?;
+            attempt_request
+                .headers_mut()
+                .insert(header::AUTHORIZATION, authorization);
+
+            let (err, retry_after) = match self.http.client.execute(attempt_request).await {
+                Ok(response) => {
+                    let retry_after = retry_after(&response);
+                    match read_json(response).await {
+                        Ok(value) => return Ok(value),
+                        Err(err) => (err, retry_after),
+                    }
+                }
+                Err(err) => (GError::Http(err), None),
+            };
+
+            if is_unauthorized(&err) && !refreshed_token {
+                refreshed_token = true;
+                self.credentials.invalidate().await?;
+                continue;
+            }
+            if attempt >= self.http.max_attempts || !is_retryable(&err, idempotent) {
+                return Err(err);
+            }
+
+            let delay = retry_after.unwrap_or_else(|| backoff_delay(attempt));
+            warn!(
+                "{} {} failed, retrying in {:.1}s (attempt {}/{}): {}",
+                request.method(),
+                request.url().path(),
+                delay.as_secs_f64(),
+                attempt + 1,
+                self.http.max_attempts,
+                err
+            );
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Precondition failures mean the instance's settings version or etag is stale
+fn is_conflict(err: &GError) -> bool {
+    return match err {
+        GError::Api(error) => {
+            error.code == 412
+                || error
+                    .errors
+                    .iter()
+                    .any(|item| matches!(item.reason.as_str(), "staleData" | "conditionNotMet"))
+        }
+        GError::HttpStatus { status, .. } => *status == 412,
+        _ => false,
+    };
+}
+
+fn is_unauthorized(err: &GError) -> bool {
+    return match err {
+        GError::Api(error) => error.code == 401,
+        GError::HttpStatus { status, .. } => *status == 401,
+        _ => false,
+    };
+}
+
+/// Rate limited and failed connections are never processed, other transient failures might have
+/// been so they're only retried for idempotent requests
+fn is_retryable(err: &GError, idempotent: bool) -> bool {
+    let status = match err {
+        GError::Http(err) if err.is_connect() => return true,
+        GError::Http(err) => return idempotent && !err.is_builder() && !err.is_redirect(),
+        GError::Api(error) => error.code,
+        GError::HttpStatus { status, .. } => *status,
+        _ => return false,
+    };
+
+    return match status {
+        429 => true,
+        408 | 500 | 502 | 503 | 504 => idempotent,
+        _ => false,
+    };
+}
+
+fn backoff_delay(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY
+        .saturating_mul(2_u32.saturating_pow(attempt - 1))
+        .min(RETRY_MAX_DELAY);
+
+    return utils::jitter(delay);
+}
+
+/// `Retry-After` in either delay-seconds or HTTP-date form
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => (DateTime::parse_from_rfc2822(value)
+            .ok()?
+            .with_timezone(&Utc)
+            - Utc::now())
+        .to_std()
+        .unwrap_or_default(),
+    };
+
+    return Some(delay.min(RETRY_MAX_DELAY));
+}
+
+async fn read_json<T: DeserializeOwned>(response: Response) -> GResult<T> {
+    let status = response.status();
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        return Err(match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(ErrorResponse { error }) => GError::Api(Box::new(error)),
+            Err(_) => GError::HttpStatus {
+                status: status.as_u16(),
+                body,
+            },
+        });
+    }
+
+    return serde_json::from_str(&body).map_err(|source| GError::Json { source, body });
+}
+
+/// Cloud SQL Admin API v1
+#[derive(Clone, Debug)]
+pub struct SqlAdminClient {
+    api: ApiClient,
+    base_url: String,
+}
+
+impl SqlAdminClient {
+    pub const DEFAULT_BASE_URL: &str = "https://sqladmin.googleapis.com/v1";
+
+    pub fn new(api: ApiClient) -> Self {
+        return Self {
+            api,
+            base_url: Self::DEFAULT_BASE_URL.into(),
+        };
+    }
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        return self;
+    }
+    pub fn credentials(&self) -> &Credentials {
+        return self.api.credentials();
+    }
+    pub fn http(&self) -> &HttpClient {
+        return self.api.http();
+    }
+    pub async fn fetch_instances(
+        &self,
+        project_id: &str,
+        page_size: Option<u32>,
+        mut on_page: impl FnMut(usize),
+    ) -> GResult<Vec<Instance>> {
+        let mut instances = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut request = self
+                .api
+                .http
+                .client
+                .get(format!(
+                    "{}/projects/{}/instances",
+                    self.base_url, project_id
+                ))
+                .query(&[("filter", "state:RUNNABLE instanceType:CLOUD_SQL_INSTANCE")]);
+            if let Some(page_size) = page_size {
+                request = request.query(&[("maxResults", page_size)]);
+            }
+            if let Some(page_token) = &page_token {
+                request = request.query(&[("pageToken", page_token)]);
+            }
+            let response: InstancesResponse = self.api.send_json(request).await?;
+
+            instances.extend(response.items);
+            on_page(instances.len());
+
+            match response.next_page_token {
+                Some(next_page_token) if !next_page_token.is_empty() => {
+                    page_token = Some(next_page_token);
+                }
+                _ => return Ok(instances),
+            }
+        }
+    }
+    pub async fn fetch_instance(&self, project_id: &str, instance_id: &str) -> GResult<Instance> {
+        let request = self.api.http.client.get(format!(
+            "{}/projects/{}/instances/{}",
+            self.base_url, project_id, instance_id
+        ));
+        let instance: Instance = self.api.send_json(request).await?;
+
+        return Ok(instance);
+    }
+    pub async fn update_instance_network(
+        &self,
+        instance: &Instance,
+        network_update_dto: NetworkUpdateDto,
+    ) -> GResult<Operation> {
+        return self
+            .modify_instance_networks(instance, |instance, authorized_networks| {
+                let network = authorized_networks
+                    .iter_mut()
+                    .find(|network| network.name == network_update_dto.name)
+                    .ok_or_else(|| {
+                        GError::NotFound(format!(
+                            "Unable to find the network `{}`, instance[{}] project[{}]",
+                            network_update_dto.name, instance.name, instance.project
+                        ))
+                    })?;
+                network.value = network_update_dto.value.clone();
+
+                return Ok(());
+            })
+            .await;
+    }
+    pub async fn add_instance_network(
+        &self,
+        instance: &Instance,
+        network: AuthorizedNetwork,
+    ) -> GResult<Operation> {
+        return self
+            .modify_instance_networks(instance, |instance, authorized_networks| {
+                if let Some(existing) = authorized_networks
+                    .iter()
+                    .find(|existing| existing.name == network.name)
+                {
+                    return Err(GError::InvalidInput(format!(
+                        "Network `{}` already exists with value `{}`, instance[{}] project[{}]",
+                        existing.name, existing.value, instance.name, instance.project
+                    )));
+                }
+                let value = utils::normalize_network_value(&network.value);
+                if let Some(existing) = authorized_networks.iter().find(|existing| {
+                    match (&value, utils::normalize_network_value(&existing.value)) {
+                        (Some(value), Some(existing_value)) => *value == existing_value,
+                        _ => existing.value == network.value,
+                    }
+                }) {
+                    return Err(GError::InvalidInput(format!(
+                        "Value `{}` is already authorized as network `{}`, instance[{}] project[{}]",
+                        existing.value, existing.name, instance.name, instance.project
+                    )));
+                }
+                authorized_networks.push(network.clone());
+
+                return Ok(());
+            })
+            .await;
+    }
+    pub async fn remove_instance_networks(
+        &self,
+        instance: &Instance,
+        names: &[String],
+    ) -> GResult<Operation> {
+        return self
+            .modify_instance_networks(instance, |_, authorized_networks| {
+                authorized_networks.retain(|network| !names.contains(&network.name));
+
+                return Ok(());
+            })
+            .await;
+    }
+    /// Applies `modify` to the instance's authorized networks and updates them guarded by the
+    /// instance's settings version, re-fetching the instance and re-applying the change when someone else
+    /// updated it in the meantime
+    pub async fn modify_instance_networks(
+        &self,
+        instance: &Instance,
+        modify: impl Fn(&Instance, &mut Vec<AuthorizedNetwork>) -> GResult<()>,
+    ) -> GResult<Operation> {
+        let mut refetched_instance: Option<Instance> = None;
+        let mut attempt = 1;
+
+        loop {
+            let current_instance = refetched_instance.as_ref().unwrap_or(instance);
+            let mut authorized_networks = current_instance
+                .settings
+                .ip_configuration
+                .authorized_networks
+                .clone();
+            modify(current_instance, &mut authorized_networks)?;
+
+            match self
+                .update_instance_networks(current_instance, authorized_networks)
+                .await
+            {
+                Err(err) if is_conflict(&err) && attempt < MAX_CONFLICT_ATTEMPTS => {
+                    warn!(
+                        "Instance `{}` was modified concurrently, re-applying the change (attempt {}/{})",
+                        current_instance.name,
+                        attempt + 1,
+                        MAX_CONFLICT_ATTEMPTS
+                    );
+                    let instance = self
+                        .fetch_instance(&current_instance.project, &current_instance.name)
+                        .await?;
+                    refetched_instance = Some(instance);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    /// Replaces the instance's authorized networks, failing with a 412 if the instance changed
+    /// since its settings version and etag were read
+    pub async fn update_instance_networks(
+        &self,
+        instance: &Instance,
+        authorized_networks: Vec<AuthorizedNetwork>,
+    ) -> GResult<Operation> {
+        for network in &authorized_networks {
+            validate_network_value(&network.value).map_err(|err| {
+                GError::InvalidInput(format!("{}, network[{}]", err, network.name))
+            })?;
+        }
+
+        let instance_update_dto = InstanceUpdateDto {
+            etag: instance.etag.clone(),
+            settings: SettingsUpdateDto {
+                settings_version: instance.settings.extra.get("settingsVersion").cloned(),
+                ip_configuration: IpConfigurationUpdateDto {
+                    authorized_networks,
+                },
+            },
+        };
+
+        let request = self
+            .api
+            .http
+            .client
+            .patch(format!(
+                "{}/projects/{}/instances/{}",
+                self.base_url, instance.project, instance.name
+            ))
+            .json(&instance_update_dto);
+        let operation: Operation = self.api.send_json(request).await?;
+
+        return Ok(operation);
+    }
+    pub async fn fetch_operation(
+        &self,
+        project_id: &str,
+        operation_id: &str,
+    ) -> GResult<Operation> {
+        let request = self.api.http.client.get(format!(
+            "{}/projects/{}/operations/{}",
+            self.base_url, project_id, operation_id,
+        ));
+        let operation: Operation = self.api.send_json(request).await?;
+
+        return Ok(operation);
+    }
+    /// Most recent operations of the instance, newest first
+    pub async fn fetch_operations(
+        &self,
+        project_id: &str,
+        instance_id: &str,
+        max_results: u32,
+    ) -> GResult<Vec<Operation>> {
+        let request = self
+            .api
+            .http
+            .client
+            .get(format!(
+                "{}/projects/{}/operations",
+                self.base_url, project_id
+            ))
+            .query(&[("instance", instance_id)])
+            .query(&[("maxResults", max_results)]);
+        let response: OperationsResponse = self.api.send_json(request).await?;
+
+        return Ok(response.items);
+    }
+    pub async fn cancel_operation(&self, project_id: &str, operation_id: &str) -> GResult<()> {
+        let request = self.api.http.client.post(format!(
+            "{}/projects/{}/operations/{}/cancel",
+            self.base_url, project_id, operation_id,
+        ));
+        let _: IgnoredAny = self.api.send_json(request).await?;
+
+        return Ok(());
+    }
+    /// Polls the operation with capped backoff until it's done or `timeout` has elapsed, calling
+    /// `on_poll` with each fetched state
+    pub async fn wait_for_operation(
+        &self,
+        project_id: &str,
+        operation_id: &str,
+        timeout: Duration,
+        mut on_poll: impl FnMut(&Operation),
+    ) -> GResult<Operation> {
+        let started = Instant::now();
+        let mut delay = OPERATION_POLL_BASE_DELAY;
+
+        loop {
+            let operation = self.fetch_operation(project_id, operation_id).await?;
+            on_poll(&operation);
+
+            match operation.status {
+                OperationStatus::Done => {
+                    if let Some(message) = operation.error_message() {
+                        return Err(GError::OperationFailed(format!(
+                            "Operation `{}` failed: {}",
+                            operation.name, message
+                        )));
+                    }
+                    return Ok(operation);
+                }
+                OperationStatus::Unspecified => {
+                    return Err(GError::Unknown(format!(
+                        "Operation `{}` has an unknown status",
+                        operation.name
+                    )));
+                }
+                OperationStatus::Pending | OperationStatus::Running => {}
+            }
+
+            let remaining = timeout.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                return Err(GError::Timeout(format!(
+                    "Operation `{}` did not finish within {}s, it keeps running in the background",
+                    operation.name,
+                    timeout.as_secs()
+                )));
+            }
+
+            sleep(delay.min(remaining)).await;
+            delay = (delay * 2).min(OPERATION_POLL_MAX_DELAY);
+        }
+    }
+}
+
+/// Cloud Resource Manager API v1
+#[derive(Clone, Debug)]
+pub struct ResourceManagerClient {
+    api: ApiClient,
+    base_url: String,
+}
+
+impl ResourceManagerClient {
+    pub const DEFAULT_BASE_URL: &str = "https://cloudresourcemanager.googleapis.com/v1";
+
+    pub fn new(api: ApiClient) -> Self {
+        return Self {
+            api,
+            base_url: Self::DEFAULT_BASE_URL.into(),
+        };
+    }
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        return self;
+    }
+    pub async fn fetch_projects(
+        &self,
+        filter: &str,
+        page_size: Option<u32>,
+        mut on_page: impl FnMut(usize),
+    ) -> GResult<Vec<Project>> {
+        let mut projects = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut request = self
+                .api
+                .http
+                .client
+                .get(format!("{}/projects", self.base_url))
+                .query(&[("filter", filter)]);
+            if let Some(page_size) = page_size {
+                request = request.query(&[("pageSize", page_size)]);
+            }
+            if let Some(page_token) = &page_token {
+                request = request.query(&[("pageToken", page_token)]);
+            }
+            let response: ProjectsResponse = self.api.send_json(request).await?;
+
+            projects.extend(response.projects);
+            on_page(projects.len());
+
+            match response.next_page_token {
+                Some(next_page_token) if !next_page_token.is_empty() => {
+                    page_token = Some(next_page_token);
+                }
+                _ => return Ok(projects),
+            }
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use console::style;
+use dialoguer::{theme::ColorfulTheme, Select};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+
+use crate::{
+    auth::Credentials,
+    client::{ApiClient, ClientOptions, Endpoints, ResourceManagerClient, SqlAdminClient},
+    config::{GConfig, DEFAULT_OPERATION_TIMEOUT_SECS, DEFAULT_PROJECT_FILTER},
+    info,
+    models::{
+        cli::{ApiArgs, WaitArgs},
+        google::{Instance, Operation, OperationStatus, Project},
+    },
+    GError, GResult,
+};
+
+/// Waits for the operation while showing its progress, or only prints its ID with `--no-wait`
+pub(crate) async fn wait_for_operation(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    project_id: &str,
+    operation: &Operation,
+    wait_args: &WaitArgs,
+) -> GResult<()> {
+    if wait_args.no_wait {
+        info!(
+            "Operation {} started, wait for it with `gsqueal operations wait {} --project {}`",
+            style(&operation.name).green().bold(),
+            operation.name,
+            project_id
+        );
+        return Ok(());
+    }
+
+    let timeout = resolve_operation_timeout(config, wait_args);
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner} {msg} [{elapsed}]")
+            .unwrap(),
+    );
+    spinner.set_message(operation_progress(operation));
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    let result = sql_admin
+        .wait_for_operation(project_id, &operation.name, timeout, |operation| {
+            spinner.set_message(operation_progress(operation))
+        })
+        .await;
+    spinner.finish_and_clear();
+    result?;
+
+    info!("Operation completed successfully!");
+
+    return Ok(());
+}
+
+/// `--timeout` if given, otherwise the configured operation timeout
+pub(crate) fn resolve_operation_timeout(config: &GConfig, wait_args: &WaitArgs) -> Duration {
+    let timeout = wait_args.timeout.unwrap_or_else(|| {
+        config
+            .api
+            .operation_timeout_secs
+            .unwrap_or(DEFAULT_OPERATION_TIMEOUT_SECS)
+    });
+
+    return Duration::from_secs(timeout);
+}
+
+fn operation_progress(operation: &Operation) -> String {
+    let status = match operation.status {
+        OperationStatus::Pending => "pending",
+        OperationStatus::Running => "running",
+        OperationStatus::Done => "done",
+        OperationStatus::Unspecified => "in an unknown state",
+    };
+    let mut progress = format!(
+        "{} operation {}",
+        operation.operation_type.as_deref().unwrap_or("Processing"),
+        status
+    );
+    if let Some(start_time) = &operation.start_time {
+        let start_time = match DateTime::parse_from_rfc3339(start_time) {
+            Ok(start_time) => start_time
+                .with_timezone(&Local)
+                .format("%H:%M:%S")
+                .to_string(),
+            Err(_) => start_time.clone(),
+        };
+        progress.push_str(&format!(", started at {}", start_time));
+    }
+
+    return progress;
+}
+
+pub(crate) struct ApiClients {
+    pub sql_admin: SqlAdminClient,
+    pub resource_manager: ResourceManagerClient,
+    pub endpoints: Endpoints,
+    /// Config the clients were built from, read once per command and passed down from here
+    pub config: GConfig,
+}
+
+/// Clients from the config, with the impersonation and endpoint overrides from the arguments
+pub(crate) async fn api_clients(api_args: &ApiArgs) -> GResult<ApiClients> {
+    let config = GConfig::get().await?;
+    let endpoints = Endpoints::resolve(&config, &api_args.api_endpoint)?;
+    let mut credentials = Credentials::from_config(&config);
+    if api_args.impersonate_service_account.is_some() {
+        credentials = credentials.impersonate(api_args.impersonate_service_account.clone());
+    }
+    if let Some(url) = &endpoints.iamcredentials {
+        credentials = credentials.with_iam_credentials_base_url(url);
+    }
+
+    let mut options = ClientOptions::from_config(&config);
+    if let Some(max_attempts) = api_args.max_attempts {
+        options.max_attempts = max_attempts;
+    }
+
+    let api = ApiClient::new(options, credentials)?;
+    let mut sql_admin = SqlAdminClient::new(api.clone());
+    if let Some(url) = &endpoints.sqladmin {
+        sql_admin = sql_admin.with_base_url(url);
+    }
+    let mut resource_manager = ResourceManagerClient::new(api);
+    if let Some(url) = &endpoints.cloudresourcemanager {
+        resource_manager = resource_manager.with_base_url(url);
+    }
+
+    return Ok(ApiClients {
+        sql_admin,
+        resource_manager,
+        endpoints,
+        config,
+    });
+}
+
+/// Names the impersonated service account in confirmation prompts
+pub(crate) fn impersonation_notice(credentials: &Credentials) -> String {
+    return match credentials.impersonated_service_account() {
+        Some(service_account) => format!(" as {}", style(service_account).green().bold()),
+        None => String::new(),
+    };
+}
+
+fn resolve_page_size(config: &GConfig, api_args: &ApiArgs) -> Option<u32> {
+    return api_args.page_size.or(config.api.page_size);
+}
+
+pub(crate) async fn resolve_project(
+    resource_manager: &ResourceManagerClient,
+    config: &GConfig,
+    project: &Option<String>,
+    api_args: &ApiArgs,
+) -> GResult<String> {
+    return match project {
+        Some(project_id) => Ok(project_id.clone()),
+        None => select_project(resource_manager, config, api_args).await,
+    };
+}
+
+pub(crate) async fn resolve_instance(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    project_id: &str,
+    instance: &Option<String>,
+    api_args: &ApiArgs,
+) -> GResult<Instance> {
+    return match instance {
+        Some(instance_id) => sql_admin.fetch_instance(project_id, instance_id).await,
+        None => select_instance(sql_admin, config, project_id, api_args).await,
+    };
+}
+
+async fn fetch_projects(
+    resource_manager: &ResourceManagerClient,
+    config: &GConfig,
+    api_args: &ApiArgs,
+) -> GResult<Vec<Project>> {
+    if api_args.project_filter.is_none() && !config.projects.pinned.is_empty() {
+        return Ok(config
+            .projects
+            .pinned
+            .iter()
+            .map(|project_id| Project {
+                name: project_id.clone(),
+                project_id: project_id.clone(),
+            })
+            .collect());
+    }
+
+    let filter = match &api_args.project_filter {
+        Some(filter) => filter.clone(),
+        None => config
+            .projects
+            .filter
+            .clone()
+            .unwrap_or_else(|| DEFAULT_PROJECT_FILTER.into()),
+    };
+    let page_size = resolve_page_size(config, api_args);
+    let fetching_projects_spinner = ProgressBar::new_spinner();
+    fetching_projects_spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner} Fetching projects... {msg}")
+            .unwrap(),
+    );
+    fetching_projects_spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let projects = resource_manager
+        .fetch_projects(&filter, page_size, |count| {
+            fetching_projects_spinner.set_message(format!("{} found", count));
+        })
+        .await;
+    fetching_projects_spinner.finish_and_clear();
+
+    return projects;
+}
+
+pub(crate) async fn fetch_instances(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    project_id: &str,
+    api_args: &ApiArgs,
+) -> GResult<Vec<Instance>> {
+    let page_size = resolve_page_size(config, api_args);
+    let spinner_template = format!(
+        "{{spinner}} Fetching {}'s instances... {{msg}}",
+        style(project_id).green(),
+    );
+    let fetching_instances_spinner = ProgressBar::new_spinner();
+    fetching_instances_spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template(&spinner_template)
+            .unwrap(),
+    );
+    fetching_instances_spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let instances = sql_admin
+        .fetch_instances(project_id, page_size, |count| {
+            fetching_instances_spinner.set_message(format!("{} found", count));
+        })
+        .await;
+    fetching_instances_spinner.finish_and_clear();
+
+    return instances;
+}
+
+async fn select_project(
+    resource_manager: &ResourceManagerClient,
+    config: &GConfig,
+    api_args: &ApiArgs,
+) -> GResult<String> {
+    let mut projects = fetch_projects(resource_manager, config, api_args).await?;
+    if projects.is_empty() {
+        return Err(GError::NotFound(
+            "Cannot find any projects under your account".into(),
+        ));
+    }
+    projects.sort_by_key(|project| project.name.to_lowercase());
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a project")
+        .default(0)
+        .max_length(10)
+        .items(
+            &projects
+                .iter()
+                .map(|project| project.name.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .interact()?;
+
+    return Ok(projects[selection].project_id.clone());
+}
+
+async fn select_instance(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    project_id: &str,
+    api_args: &ApiArgs,
+) -> GResult<Instance> {
+    let mut instances = fetch_instances(sql_admin, config, project_id, api_args).await?;
+    if instances.is_empty() {
+        return Err(GError::NotFound(format!(
+            "Cannot find any instances under your account for project `{}`",
+            project_id
+        )));
+    }
+
+    let instance_selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select an instance")
+        .default(0)
+        .max_length(10)
+        .items(
+            &instances
+                .iter()
+                .map(|instance| instance.name.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .interact()?;
+
+    return Ok(instances.swap_remove(instance_selection));
+}
+
+/// Prints aligned columns with a bold header, `highlighted` rows are shown in green
+pub(crate) fn print_table<const N: usize>(
+    headers: [&str; N],
+    rows: &[[String; N]],
+    highlighted: &[bool],
+) {
+    let mut widths = headers.map(str::len);
+    for row in rows {
+        for (width, field) in widths.iter_mut().zip(row) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+
+    let header = headers
+        .iter()
+        .zip(widths)
+        .map(|(header, width)| format!("{:<width$}", header, width = width))
+        .collect::<Vec<_>>()
+        .join("  ");
+    println!("{}", style(header.trim_end()).bold());
+
+    for (index, row) in rows.iter().enumerate() {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(field, width)| format!("{:<width$}", field, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        if highlighted.get(index).copied().unwrap_or(false) {
+            println!("{}", style(line.trim_end()).green());
+        } else {
+            println!("{}", line.trim_end());
+        }
+    }
+}
+
+pub(crate) fn print_csv<const N: usize>(headers: [&str; N], rows: &[[String; N]]) {
+    let header = headers
+        .iter()
+        .map(|header| header.to_lowercase().replace(' ', "_"))
+        .collect::<Vec<_>>()
+        .join(",");
+    println!("{}", header);
+
+    for row in rows {
+        let line = row
+            .iter()
+            .map(|field| {
+                if field.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        println!("{}", line);
+    }
+}
+
+pub(crate) fn print_json<T: Serialize>(value: &T) -> GResult<()> {
+    let serialized_data = serde_json::to_string_pretty(value)
+        .map_err(|err| GError::Unknown(format!("Failed to serialize data: {}", err)))?;
+    println!("{}", serialized_data);
+
+    return Ok(());
+}
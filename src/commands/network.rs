@@ -1,236 +1,895 @@
-use std::time::Duration;
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    net::IpAddr,
+    time::Duration,
+};
 
 use console::style;
-use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use dialoguer::{theme::ColorfulTheme, Confirm, MultiSelect, Select};
+use glob::Pattern;
 use indicatif::{ProgressBar, ProgressStyle};
+use tokio::task::JoinSet;
 
+use super::common::{
+    api_clients, fetch_instances, impersonation_notice, print_csv, print_json, print_table,
+    resolve_instance, resolve_operation_timeout, resolve_project, wait_for_operation, ApiClients,
+};
 use crate::{
-    error, info,
-    models::{NetworkOperation, NetworkUpdateDto},
+    client::{Endpoints, SqlAdminClient},
+    config::{GConfig, DEFAULT_IPV6_PREFIX, DEFAULT_PREFIX},
+    info,
+    models::{
+        cli::{
+            IpArgs, NetworkAddArgs, NetworkListArgs, NetworkRemoveArgs, NetworkSaveArgs,
+            NetworkTargetsListArgs, NetworkTargetsRemoveArgs, NetworkUpdateArgs, OutputFormat,
+            WaitArgs,
+        },
+        google::{AuthorizedNetwork, Instance},
+        NetworkListItem, NetworkOperation, NetworkTargetItem, NetworkTargetUpdateItem,
+        NetworkTargetUpdateStatus, NetworkUpdateDto,
+    },
     store::GStore,
-    utils, warn,
+    utils, warn, GError, GResult,
 };
 
-pub async fn update(repeat_last: &bool) {
-    if *repeat_last {
-        let store = GStore::get().await;
-
-        if let Some(operation) = store.network.last_operation {
-            let NetworkOperation {
-                project_id,
-                instance_id,
-                network_name,
-            } = operation;
-
-            let instance = match utils::fetch_instance(&project_id, &instance_id).await {
-                Ok(instance) => instance,
-                Err(_) => {
-                    error!(
-                        "Cannot fetch instance `{}` under project `{}`",
-                        &project_id, &instance_id,
-                    );
-                    std::process::exit(1);
-                }
-            };
-            let ip = utils::current_ip_cidr_notation().await;
-            let network = instance
-                .settings
-                .ip_configuration
-                .authorized_networks
-                .iter()
-                .find(|network| network.name == network_name)
-                .unwrap_or_else(|| {
-                    error!(
-                        "Unable to find the network `{}`, instance[{}] project[{}]",
-                        network_name, instance.name, instance.project
-                    );
-                    std::process::exit(1);
-                });
+pub async fn update(args: &NetworkUpdateArgs) -> GResult<()> {
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        endpoints,
+        config,
+    } = api_clients(&args.api).await?;
+    if args.all_saved {
+        let store = GStore::get().await?;
+        if store.network.targets.is_empty() {
+            return Err(GError::NotFound(
+                "Cannot find any saved targets, save one with `gsqueal network save <alias>`"
+                    .into(),
+            ));
+        }
+
+        let spinner = ProgressBar::new_spinner();
+        spinner.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner} {msg} [{elapsed}]")
+                .unwrap(),
+        );
+        spinner.set_message(format!(
+            "Updating {} saved targets",
+            store.network.targets.len()
+        ));
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        let result = async {
+            let current_ips = current_target_ips(
+                &sql_admin,
+                &config,
+                &endpoints,
+                &store.network.targets,
+                &args.ip,
+            )
+            .await?;
+            return update_saved_targets(
+                &sql_admin,
+                &config,
+                &store.network.targets,
+                &current_ips,
+                &args.ip,
+                args.prefix,
+                &args.wait,
+            )
+            .await;
+        }
+        .await;
+        spinner.finish_and_clear();
+        let items = result?;
+
+        let rows: Vec<_> = items.iter().map(target_update_item_fields).collect();
+        let highlighted: Vec<_> = items
+            .iter()
+            .map(|item| item.status == NetworkTargetUpdateStatus::Updated)
+            .collect();
+        print_table(TARGET_UPDATE_HEADERS, &rows, &highlighted);
+
+        let failed = items
+            .iter()
+            .filter(|item| item.status == NetworkTargetUpdateStatus::Failed)
+            .count();
+        if failed > 0 {
+            return Err(GError::OperationFailed(format!(
+                "{} of {} saved targets failed to update",
+                failed,
+                items.len()
+            )));
+        }
+    } else if args.repeat_last || args.target.is_some() {
+        let store = GStore::get().await?;
+        let target = match &args.target {
+            Some(alias) => store.network.targets.get(alias).cloned().ok_or_else(|| {
+                GError::NotFound(format!(
+                    "Cannot find a saved target named `{}`, see `gsqueal network targets list`",
+                    alias
+                ))
+            })?,
+            None => store.network.last_operation.ok_or_else(|| {
+                GError::NotFound("Cannot find any previous network update operations".into())
+            })?,
+        };
+
+        update_target(&sql_admin, &config, &endpoints, target, args).await?;
+    } else {
+        let project_id =
+            resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+        let selected_instance =
+            resolve_instance(&sql_admin, &config, &project_id, &args.instance, &args.api).await?;
+        let selected_network = match &args.network {
+            Some(network_name) => find_network(&selected_instance, network_name)?.clone(),
+            None => select_network(&selected_instance)?,
+        };
+
+        let prefix = resolve_prefix(&config, args.prefix, args.ip.is_ipv6())?;
+        let ip = utils::current_ip_cidr_notation(
+            sql_admin.http(),
+            &config,
+            prefix,
+            &args.ip,
+            endpoints.ip.as_deref(),
+        )
+        .await?;
+
+        if selected_network.value == ip {
+            warn!("Skip update, network is already set to your current IP.");
+        } else {
+            let prompt = format!(
+                "Update {} to current IP {}{}. Continue?",
+                style(&selected_network.name).green().bold(),
+                style(&ip).green().bold(),
+                impersonation_notice(sql_admin.credentials()),
+            );
+            let confirmation = args.yes || Confirm::new().with_prompt(prompt).interact()?;
 
-            if network.value == ip {
-                warn!("Skip update, network is already set to your current IP.");
+            if !confirmation {
+                return Err(GError::Aborted("Aborting...".into()));
             } else {
                 let network_update_dto = NetworkUpdateDto {
-                    name: network.name.clone(),
+                    name: selected_network.name.clone(),
                     value: ip,
                 };
 
-                let operation = utils::update_instance_network(&instance, network_update_dto).await;
-                let spinner = ProgressBar::new_spinner();
-                spinner.set_style(
-                    ProgressStyle::default_spinner()
-                        .template("{spinner} Processing...")
-                        .unwrap(),
-                );
-                spinner.enable_steady_tick(Duration::from_millis(100));
-                let operation_status =
-                    utils::operation_status(&instance.project, &operation.name).await;
-
-                spinner.finish_and_clear();
-
-                match operation_status {
-                    Ok(()) => {
-                        info!("Operation completed successfully! ✅");
-                    }
-                    Err(message) => {
-                        error!("Operation failed: {}", message);
-                    }
-                }
+                let operation = sql_admin
+                    .update_instance_network(&selected_instance, network_update_dto)
+                    .await?;
+                wait_for_operation(&sql_admin, &config, &project_id, &operation, &args.wait)
+                    .await?;
+
+                let mut store = GStore::get().await?;
+                store.network.last_operation = Some(NetworkOperation {
+                    project_id: project_id.to_string(),
+                    instance_id: selected_instance.name.to_string(),
+                    network_name: selected_network.name.to_string(),
+                    prefix: Some(prefix),
+                    ipv6: args.ip.is_ipv6(),
+                });
+                GStore::set(&store).await?;
             }
-        } else {
-            error!("Cannot find any previous network update operations");
-            std::process::exit(1);
         }
+    }
+
+    return Ok(());
+}
+
+/// Updates a saved or last used target's network to the current IP without prompting
+async fn update_target(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    endpoints: &Endpoints,
+    target: NetworkOperation,
+    args: &NetworkUpdateArgs,
+) -> GResult<()> {
+    let NetworkOperation {
+        project_id,
+        instance_id,
+        network_name,
+        prefix,
+        ipv6,
+    } = target;
+
+    let instance = sql_admin.fetch_instance(&project_id, &instance_id).await?;
+    let (ip_args, prefix) = target_ip_args(&args.ip, args.prefix, prefix, ipv6);
+    let prefix = resolve_prefix(config, prefix, ip_args.is_ipv6())?;
+    let ip = utils::current_ip_cidr_notation(
+        sql_admin.http(),
+        config,
+        prefix,
+        &ip_args,
+        endpoints.ip.as_deref(),
+    )
+    .await?;
+    let network = find_network(&instance, &network_name)?;
+
+    if network.value == ip {
+        warn!("Skip update, network is already set to your current IP.");
     } else {
-        let fetching_projects_spinner = ProgressBar::new_spinner();
-        fetching_projects_spinner.set_style(
-            ProgressStyle::default_spinner()
-                .template("{spinner} Fetching projects...")
-                .unwrap(),
-        );
-        fetching_projects_spinner.enable_steady_tick(Duration::from_millis(100));
+        let network_update_dto = NetworkUpdateDto {
+            name: network.name.clone(),
+            value: ip,
+        };
+
+        let operation = sql_admin
+            .update_instance_network(&instance, network_update_dto)
+            .await?;
+        wait_for_operation(sql_admin, config, &instance.project, &operation, &args.wait).await?;
+    }
 
-        let mut projects = utils::fetch_projects().await;
-        fetching_projects_spinner.finish_and_clear();
+    return Ok(());
+}
 
-        if projects.is_empty() {
-            error!("Cannot find any projects under your account");
-            std::process::exit(1);
+/// Current address of each address family used by `targets`, keyed by whether it's IPv6. Each
+/// family is discovered once rather than once per target.
+pub(crate) async fn current_target_ips(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    endpoints: &Endpoints,
+    targets: &BTreeMap<String, NetworkOperation>,
+    ip_args: &IpArgs,
+) -> GResult<HashMap<bool, IpAddr>> {
+    let mut current_ips = HashMap::new();
+    for target in targets.values() {
+        let (target_ip_args, _) = target_ip_args(ip_args, None, target.prefix, target.ipv6);
+        let ipv6 = target_ip_args.is_ipv6();
+        if let Entry::Vacant(entry) = current_ips.entry(ipv6) {
+            entry.insert(
+                utils::current_ip(
+                    sql_admin.http(),
+                    config,
+                    &target_ip_args,
+                    endpoints.ip.as_deref(),
+                )
+                .await?,
+            );
         }
-        projects.sort_by_key(|project| project.name.to_lowercase());
+    }
 
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Select a project")
-            .default(0)
-            .max_length(10)
-            .items(
-                &projects
-                    .iter()
-                    .map(|project| project.name.as_str())
-                    .collect::<Vec<_>>(),
+    return Ok(current_ips);
+}
+
+/// CIDR notation a saved target is set to, from its family's address in `current_ips`
+pub(crate) fn target_value(
+    config: &GConfig,
+    alias: &str,
+    target: &NetworkOperation,
+    current_ips: &HashMap<bool, IpAddr>,
+    ip_args: &IpArgs,
+    prefix: Option<u8>,
+) -> GResult<String> {
+    let (target_ip_args, target_prefix) =
+        target_ip_args(ip_args, prefix, target.prefix, target.ipv6);
+    let ipv6 = target_ip_args.is_ipv6();
+    let target_prefix = resolve_prefix(config, target_prefix, ipv6)?;
+    let current_ip = *current_ips.get(&ipv6).ok_or_else(|| {
+        GError::Unknown(format!(
+            "Missing current IPv{} address for target `{}`",
+            if ipv6 { 6 } else { 4 },
+            alias
+        ))
+    })?;
+
+    return utils::cidr_notation(current_ip, target_prefix);
+}
+
+/// Updates every saved target to its family's address in `current_ips`, fetching and patching
+/// instances concurrently. Targets on the same instance share a single PATCH since an instance
+/// runs one operation at a time.
+pub(crate) async fn update_saved_targets(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    targets: &BTreeMap<String, NetworkOperation>,
+    current_ips: &HashMap<bool, IpAddr>,
+    ip_args: &IpArgs,
+    prefix: Option<u8>,
+    wait_args: &WaitArgs,
+) -> GResult<Vec<NetworkTargetUpdateItem>> {
+    let mut instances: BTreeMap<(String, String), Vec<NetworkTargetUpdateItem>> = BTreeMap::new();
+    for (alias, target) in targets {
+        let value = target_value(config, alias, target, current_ips, ip_args, prefix)?;
+        instances
+            .entry((target.project_id.clone(), target.instance_id.clone()))
+            .or_default()
+            .push(NetworkTargetUpdateItem {
+                alias: alias.clone(),
+                project: target.project_id.clone(),
+                instance: target.instance_id.clone(),
+                network: target.network_name.clone(),
+                value,
+                status: NetworkTargetUpdateStatus::Skipped,
+                detail: None,
+            });
+    }
+
+    let timeout = resolve_operation_timeout(config, wait_args);
+    let mut tasks = JoinSet::new();
+    for ((project_id, instance_id), items) in instances {
+        let sql_admin = sql_admin.clone();
+        let no_wait = wait_args.no_wait;
+        tasks.spawn(async move {
+            return update_instance_targets(
+                &sql_admin,
+                &project_id,
+                &instance_id,
+                items,
+                no_wait,
+                timeout,
             )
-            .interact()
-            .unwrap();
-        let selected_project = &projects[selection];
+            .await;
+        });
+    }
 
-        let spinner_template = format!(
-            "{{spinner}} Fetching {}'s instances...",
-            style(&selected_project.project_id).green(),
-        );
-        let fetching_instances_spinner = ProgressBar::new_spinner();
-        fetching_instances_spinner.set_style(
-            ProgressStyle::default_spinner()
-                .template(&spinner_template)
-                .unwrap(),
-        );
-        fetching_instances_spinner.enable_steady_tick(Duration::from_millis(100));
+    let mut results = Vec::new();
+    while let Some(items) = tasks.join_next().await {
+        let items = items.map_err(|err| GError::Unknown(err.to_string()))?;
+        results.extend(items);
+    }
+    results.sort_by(|a, b| a.alias.cmp(&b.alias));
 
-        let instances = utils::fetch_instances(&selected_project.project_id).await;
-        fetching_instances_spinner.finish_and_clear();
+    return Ok(results);
+}
 
-        if instances.is_empty() {
-            error!(
-                "Cannot find any instances under your account for project `{}`",
-                selected_project.project_id
-            );
-            std::process::exit(1);
+/// Applies every pending target of one instance in a single operation, recording each outcome
+async fn update_instance_targets(
+    sql_admin: &SqlAdminClient,
+    project_id: &str,
+    instance_id: &str,
+    mut items: Vec<NetworkTargetUpdateItem>,
+    no_wait: bool,
+    timeout: Duration,
+) -> Vec<NetworkTargetUpdateItem> {
+    let fail = |items: &mut Vec<NetworkTargetUpdateItem>, pending: &[usize], err: GError| {
+        for &index in pending {
+            items[index].status = NetworkTargetUpdateStatus::Failed;
+            items[index].detail = Some(err.to_string());
+        }
+    };
+    let all: Vec<usize> = (0..items.len()).collect();
+
+    let instance = match sql_admin.fetch_instance(project_id, instance_id).await {
+        Ok(instance) => instance,
+        Err(err) => {
+            fail(&mut items, &all, err);
+            return items;
+        }
+    };
+
+    let mut pending = Vec::new();
+    for (index, item) in items.iter_mut().enumerate() {
+        match find_network(&instance, &item.network) {
+            Ok(network) if network.value == item.value => {}
+            Ok(_) => pending.push(index),
+            Err(err) => {
+                item.status = NetworkTargetUpdateStatus::Failed;
+                item.detail = Some(err.to_string());
+            }
+        }
+    }
+    if pending.is_empty() {
+        return items;
+    }
+
+    let updates: Vec<(String, String)> = pending
+        .iter()
+        .map(|&index| (items[index].network.clone(), items[index].value.clone()))
+        .collect();
+    let result = sql_admin
+        .modify_instance_networks(&instance, |_, authorized_networks| {
+            for network in authorized_networks.iter_mut() {
+                if let Some((_, value)) = updates.iter().find(|(name, _)| *name == network.name) {
+                    network.value = value.clone();
+                }
+            }
+
+            return Ok(());
+        })
+        .await;
+    let operation = match result {
+        Ok(operation) => operation,
+        Err(err) => {
+            fail(&mut items, &pending, err);
+            return items;
+        }
+    };
+
+    let (status, result) = if no_wait {
+        (NetworkTargetUpdateStatus::Started, Ok(()))
+    } else {
+        let result = sql_admin
+            .wait_for_operation(project_id, &operation.name, timeout, |_| {})
+            .await;
+        (NetworkTargetUpdateStatus::Updated, result.map(|_| ()))
+    };
+    match result {
+        Ok(()) => {
+            for &index in &pending {
+                items[index].status = status;
+                items[index].detail = Some(operation.name.clone());
+            }
+        }
+        Err(err) => fail(&mut items, &pending, err),
+    }
+
+    return items;
+}
+
+/// Address family and prefix for a saved target, letting the command line override both
+fn target_ip_args(
+    ip_args: &IpArgs,
+    prefix: Option<u8>,
+    saved_prefix: Option<u8>,
+    saved_ipv6: bool,
+) -> (IpArgs, Option<u8>) {
+    // The stored prefix only applies to the address family it was saved with
+    let saved_prefix = if ip_args.is_ipv6() && !saved_ipv6 {
+        None
+    } else {
+        saved_prefix
+    };
+    let ip_args = IpArgs {
+        ipv6: ip_args.ipv6 || saved_ipv6,
+        ..ip_args.clone()
+    };
+
+    return (ip_args, prefix.or(saved_prefix));
+}
+
+pub async fn add(args: &NetworkAddArgs) -> GResult<()> {
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        endpoints,
+        config,
+    } = api_clients(&args.api).await?;
+    let project_id = resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+    let selected_instance =
+        resolve_instance(&sql_admin, &config, &project_id, &args.instance, &args.api).await?;
+    let value = match &args.value {
+        Some(value) => value.clone(),
+        None => {
+            let prefix = resolve_prefix(&config, args.prefix, args.ip.is_ipv6())?;
+            utils::current_ip_cidr_notation(
+                sql_admin.http(),
+                &config,
+                prefix,
+                &args.ip,
+                endpoints.ip.as_deref(),
+            )
+            .await?
         }
+    };
+    utils::validate_network_value(&value)?;
+    if let Some(expiration_time) = &args.expiration_time {
+        utils::validate_expiration_time(expiration_time)?;
+    }
+
+    let prompt = format!(
+        "Add {} with value {} to {}{}. Continue?",
+        style(&args.name).green().bold(),
+        style(&value).green().bold(),
+        style(&selected_instance.name).green().bold(),
+        impersonation_notice(sql_admin.credentials()),
+    );
+    let confirmation = args.yes || Confirm::new().with_prompt(prompt).interact()?;
+
+    if !confirmation {
+        return Err(GError::Aborted("Aborting...".into()));
+    }
+
+    let network = AuthorizedNetwork {
+        name: args.name.clone(),
+        value,
+        kind: "sql#aclEntry".into(),
+        expiration_time: args.expiration_time.clone(),
+        extra: Default::default(),
+    };
+    let operation = sql_admin
+        .add_instance_network(&selected_instance, network)
+        .await?;
+    wait_for_operation(&sql_admin, &config, &project_id, &operation, &args.wait).await?;
 
-        let instance_selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Select an instance")
-            .default(0)
+    return Ok(());
+}
+
+pub async fn remove(args: &NetworkRemoveArgs) -> GResult<()> {
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        config,
+        ..
+    } = api_clients(&args.api).await?;
+    let project_id = resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+    let selected_instance =
+        resolve_instance(&sql_admin, &config, &project_id, &args.instance, &args.api).await?;
+    let mut authorized_networks = selected_instance
+        .settings
+        .ip_configuration
+        .authorized_networks
+        .clone();
+    if authorized_networks.is_empty() {
+        return Err(GError::NotFound(format!(
+            "Cannot find any authorized networks for instance[{}] project[{}]",
+            selected_instance.name, selected_instance.project
+        )));
+    }
+
+    authorized_networks.sort_by_key(|network| network.name.to_lowercase());
+
+    let removed_names: Vec<String> = if args.expired {
+        authorized_networks
+            .iter()
+            .filter(|network| utils::is_network_expired(network))
+            .map(|network| network.name.clone())
+            .collect()
+    } else if !args.patterns.is_empty() {
+        let patterns: Vec<Pattern> = args
+            .patterns
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern).map_err(|err| {
+                    GError::InvalidInput(format!("Invalid pattern `{}`: {}", pattern, err))
+                })
+            })
+            .collect::<GResult<_>>()?;
+
+        authorized_networks
+            .iter()
+            .filter(|network| {
+                patterns
+                    .iter()
+                    .any(|pattern| pattern.matches(&network.name))
+            })
+            .map(|network| network.name.clone())
+            .collect()
+    } else {
+        let selections = MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt("Select networks to remove")
             .max_length(10)
             .items(
-                &instances
+                &authorized_networks
                     .iter()
-                    .map(|instance| instance.name.as_str())
+                    .map(|network| format!("{} ({})", network.name, network.value))
                     .collect::<Vec<_>>(),
             )
-            .interact()
-            .unwrap();
-        let selected_instance = &instances[instance_selection];
-        let mut authorized_networks = selected_instance
+            .interact()?;
+
+        selections
+            .into_iter()
+            .map(|selection| authorized_networks[selection].name.clone())
+            .collect()
+    };
+
+    if removed_names.is_empty() {
+        warn!("Skip update, no networks matched for removal.");
+        return Ok(());
+    }
+
+    println!("{}", style("Authorized networks:").bold());
+    for network in &authorized_networks {
+        let line = format!("{} {}", network.name, network.value);
+        if removed_names.contains(&network.name) {
+            println!("{}", style(format!("- {}", line)).red());
+        } else {
+            println!("  {}", line);
+        }
+    }
+
+    let prompt = format!(
+        "Remove {} of {} networks from {}{}. Continue?",
+        style(removed_names.len()).green().bold(),
+        style(authorized_networks.len()).green().bold(),
+        style(&selected_instance.name).green().bold(),
+        impersonation_notice(sql_admin.credentials()),
+    );
+    let confirmation = args.yes || Confirm::new().with_prompt(prompt).interact()?;
+
+    if !confirmation {
+        return Err(GError::Aborted("Aborting...".into()));
+    }
+
+    let operation = sql_admin
+        .remove_instance_networks(&selected_instance, &removed_names)
+        .await?;
+    wait_for_operation(&sql_admin, &config, &project_id, &operation, &args.wait).await?;
+
+    return Ok(());
+}
+
+pub async fn list(args: &NetworkListArgs) -> GResult<()> {
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        endpoints,
+        config,
+    } = api_clients(&args.api).await?;
+    let project_id = resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+    let instances = if args.all_instances {
+        let mut instances = fetch_instances(&sql_admin, &config, &project_id, &args.api).await?;
+        instances.sort_by_key(|instance| instance.name.to_lowercase());
+        instances
+    } else {
+        vec![resolve_instance(&sql_admin, &config, &project_id, &args.instance, &args.api).await?]
+    };
+    // Only the CURRENT IP column depends on it, so don't fail listings from offline machines
+    let ip = match utils::current_ip(sql_admin.http(), &config, &args.ip, endpoints.ip.as_deref())
+        .await
+    {
+        Ok(ip) => Some(ip),
+        Err(err @ GError::InvalidInput(_)) => return Err(err),
+        Err(err) => {
+            warn!("{}, CURRENT IP is shown as unknown", err);
+            None
+        }
+    };
+
+    let mut items = Vec::new();
+    for instance in &instances {
+        let mut authorized_networks = instance
             .settings
             .ip_configuration
             .authorized_networks
             .clone();
-        if authorized_networks.is_empty() {
-            error!(
-                "Cannot find any authorized networks for instance[{}] project[{}]",
-                selected_instance.name, selected_instance.project
-            );
-            std::process::exit(1);
+        authorized_networks.sort_by_key(|network| network.name.to_lowercase());
+
+        items.extend(
+            authorized_networks
+                .into_iter()
+                .map(|network| NetworkListItem {
+                    project: instance.project.clone(),
+                    instance: instance.name.clone(),
+                    current_ip: ip.map(|ip| utils::network_contains(&network.value, ip)),
+                    name: network.name,
+                    value: network.value,
+                    kind: network.kind,
+                    expiration_time: network.expiration_time,
+                }),
+        );
+    }
+
+    let rows: Vec<_> = items.iter().map(list_item_fields).collect();
+    match args.output {
+        OutputFormat::Table => {
+            let highlighted: Vec<_> = items
+                .iter()
+                .map(|item| item.current_ip == Some(true))
+                .collect();
+            print_table(LIST_HEADERS, &rows, &highlighted);
         }
+        OutputFormat::Json => print_json(&items)?,
+        OutputFormat::Csv => print_csv(LIST_HEADERS, &rows),
+    }
 
-        authorized_networks.sort_by_key(|network| network.name.to_lowercase());
+    return Ok(());
+}
 
-        let network_selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Select a network")
-            .default(0)
-            .max_length(10)
-            .items(
-                &authorized_networks
-                    .iter()
-                    .map(|network| network.name.as_str())
-                    .collect::<Vec<_>>(),
-            )
-            .interact()
-            .unwrap();
-        let selected_network = authorized_networks[network_selection].clone();
+pub async fn save(args: &NetworkSaveArgs) -> GResult<()> {
+    validate_alias(&args.alias)?;
 
-        let ip = utils::current_ip_cidr_notation().await;
+    let mut store = GStore::get().await?;
+    if store.network.targets.contains_key(&args.alias) && !args.force {
+        return Err(GError::InvalidInput(format!(
+            "Target `{}` already exists, use --force to overwrite it",
+            args.alias
+        )));
+    }
 
-        if selected_network.value == ip {
-            warn!("Skip update, network is already set to your current IP.");
-        } else {
-            let prompt = format!(
-                "Update {} to current IP {}. Continue?",
-                style(&selected_network.name).green().bold(),
-                style(&ip).green().bold(),
-            );
-            let confirmation = Confirm::new().with_prompt(prompt).interact().unwrap();
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        config,
+        ..
+    } = api_clients(&args.api).await?;
+    let project_id = resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+    let selected_instance =
+        resolve_instance(&sql_admin, &config, &project_id, &args.instance, &args.api).await?;
+    let selected_network = match &args.network {
+        Some(network_name) => find_network(&selected_instance, network_name)?.clone(),
+        None => select_network(&selected_instance)?,
+    };
+    if let Some(prefix) = args.prefix {
+        utils::validate_prefix(prefix, args.ipv6)?;
+    }
 
-            if confirmation == false {
-                warn!("Aborting...");
-                std::process::exit(1);
-            } else {
-                let network_update_dto = NetworkUpdateDto {
-                    name: selected_network.name.clone(),
-                    value: ip,
-                };
+    store.network.targets.insert(
+        args.alias.clone(),
+        NetworkOperation {
+            project_id,
+            instance_id: selected_instance.name,
+            network_name: selected_network.name,
+            prefix: args.prefix,
+            ipv6: args.ipv6,
+        },
+    );
+    store.set().await?;
 
-                let operation =
-                    utils::update_instance_network(selected_instance, network_update_dto).await;
-
-                let spinner = ProgressBar::new_spinner();
-                spinner.set_style(
-                    ProgressStyle::default_spinner()
-                        .template("{spinner} Processing...")
-                        .unwrap(),
-                );
-                spinner.enable_steady_tick(Duration::from_millis(100));
-                let operation_status =
-                    utils::operation_status(&selected_project.project_id, &operation.name).await;
-
-                spinner.finish_and_clear();
-
-                match operation_status {
-                    Ok(()) => {
-                        let mut store = GStore::get().await;
-                        store.network.last_operation = Some(NetworkOperation {
-                            project_id: selected_project.project_id.to_string(),
-                            instance_id: selected_instance.name.to_string(),
-                            network_name: selected_network.name.to_string(),
-                        });
-                        GStore::set(&store).await;
-                        info!("Operation completed successfully!");
-                    }
-                    Err(message) => {
-                        error!("Operation failed: {}", message);
-                        std::process::exit(1);
-                    }
-                }
+    info!(
+        "Target {} saved, update it with `gsqueal network update --target {}`",
+        args.alias, args.alias
+    );
+
+    return Ok(());
+}
+
+pub async fn list_targets(args: &NetworkTargetsListArgs) -> GResult<()> {
+    let store = GStore::get().await?;
+    let items: Vec<_> = store
+        .network
+        .targets
+        .into_iter()
+        .map(|(alias, target)| NetworkTargetItem {
+            alias,
+            project: target.project_id,
+            instance: target.instance_id,
+            network: target.network_name,
+            prefix: target.prefix,
+            ipv6: target.ipv6,
+        })
+        .collect();
+
+    let rows: Vec<_> = items.iter().map(target_item_fields).collect();
+    match args.output {
+        OutputFormat::Table => print_table(TARGET_HEADERS, &rows, &[]),
+        OutputFormat::Json => print_json(&items)?,
+        OutputFormat::Csv => print_csv(TARGET_HEADERS, &rows),
+    }
+
+    return Ok(());
+}
+
+pub async fn remove_targets(args: &NetworkTargetsRemoveArgs) -> GResult<()> {
+    let mut store = GStore::get().await?;
+    if let Some(alias) = args
+        .aliases
+        .iter()
+        .find(|alias| !store.network.targets.contains_key(*alias))
+    {
+        return Err(GError::NotFound(format!(
+            "Cannot find a saved target named `{}`",
+            alias
+        )));
+    }
+
+    for alias in &args.aliases {
+        store.network.targets.remove(alias);
+    }
+    store.set().await?;
+
+    info!("Removed {} saved target(s)", args.aliases.len());
+
+    return Ok(());
+}
+
+fn target_update_item_fields(item: &NetworkTargetUpdateItem) -> [String; 7] {
+    return [
+        item.alias.clone(),
+        item.project.clone(),
+        item.instance.clone(),
+        item.network.clone(),
+        item.value.clone(),
+        item.status.to_string(),
+        item.detail.clone().unwrap_or_default(),
+    ];
+}
+
+const TARGET_UPDATE_HEADERS: [&str; 7] = [
+    "ALIAS", "PROJECT", "INSTANCE", "NETWORK", "VALUE", "STATUS", "DETAIL",
+];
+
+/// Aliases end up in shell commands and table output, so keep them to simple identifiers
+fn validate_alias(alias: &str) -> GResult<()> {
+    let is_valid = !alias.is_empty()
+        && alias
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !is_valid {
+        return Err(GError::InvalidInput(format!(
+            "Invalid target name `{}`, use letters, digits, `-`, `_` or `.`",
+            alias
+        )));
+    }
+
+    return Ok(());
+}
+
+fn target_item_fields(item: &NetworkTargetItem) -> [String; 6] {
+    return [
+        item.alias.clone(),
+        item.project.clone(),
+        item.instance.clone(),
+        item.network.clone(),
+        item.prefix
+            .map(|prefix| prefix.to_string())
+            .unwrap_or_default(),
+        item.ipv6.to_string(),
+    ];
+}
+
+const TARGET_HEADERS: [&str; 6] = ["ALIAS", "PROJECT", "INSTANCE", "NETWORK", "PREFIX", "IPV6"];
+
+fn list_item_fields(item: &NetworkListItem) -> [String; 7] {
+    return [
+        item.project.clone(),
+        item.instance.clone(),
+        item.name.clone(),
+        item.value.clone(),
+        item.kind.clone(),
+        item.expiration_time.clone().unwrap_or_default(),
+        item.current_ip
+            .map_or_else(|| "unknown".into(), |current_ip| current_ip.to_string()),
+    ];
+}
+
+const LIST_HEADERS: [&str; 7] = [
+    "PROJECT",
+    "INSTANCE",
+    "NAME",
+    "VALUE",
+    "KIND",
+    "EXPIRATION TIME",
+    "CURRENT IP",
+];
+
+pub(crate) fn resolve_prefix(config: &GConfig, prefix: Option<u8>, ipv6: bool) -> GResult<u8> {
+    let prefix = match prefix {
+        Some(prefix) => prefix,
+        None => {
+            if ipv6 {
+                config.network.ipv6_prefix.unwrap_or(DEFAULT_IPV6_PREFIX)
+            } else {
+                config.network.prefix.unwrap_or(DEFAULT_PREFIX)
             }
         }
+    };
+    utils::validate_prefix(prefix, ipv6)?;
+
+    return Ok(prefix);
+}
+
+fn find_network<'a>(instance: &'a Instance, network_name: &str) -> GResult<&'a AuthorizedNetwork> {
+    return instance
+        .settings
+        .ip_configuration
+        .authorized_networks
+        .iter()
+        .find(|network| network.name == network_name)
+        .ok_or_else(|| {
+            GError::NotFound(format!(
+                "Unable to find the network `{}`, instance[{}] project[{}]",
+                network_name, instance.name, instance.project
+            ))
+        });
+}
+
+fn select_network(instance: &Instance) -> GResult<AuthorizedNetwork> {
+    let mut authorized_networks = instance
+        .settings
+        .ip_configuration
+        .authorized_networks
+        .clone();
+    if authorized_networks.is_empty() {
+        return Err(GError::NotFound(format!(
+            "Cannot find any authorized networks for instance[{}] project[{}]",
+            instance.name, instance.project
+        )));
     }
+
+    authorized_networks.sort_by_key(|network| network.name.to_lowercase());
+
+    let network_selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a network")
+        .default(0)
+        .max_length(10)
+        .items(
+            &authorized_networks
+                .iter()
+                .map(|network| network.name.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .interact()?;
+
+    return Ok(authorized_networks.swap_remove(network_selection));
 }
@@ -0,0 +1,169 @@
+use console::style;
+use dialoguer::Confirm;
+
+use super::common::{
+    api_clients, impersonation_notice, print_csv, print_json, print_table, resolve_instance,
+    resolve_project, wait_for_operation, ApiClients,
+};
+use crate::{
+    info,
+    models::{
+        cli::{
+            OperationsCancelArgs, OperationsDescribeArgs, OperationsListArgs, OperationsWaitArgs,
+            OutputFormat, WaitArgs,
+        },
+        google::{Operation, OperationStatus},
+    },
+    GError, GResult,
+};
+
+const OPERATION_HEADERS: [&str; 8] = [
+    "ID",
+    "INSTANCE",
+    "TYPE",
+    "STATUS",
+    "USER",
+    "START TIME",
+    "END TIME",
+    "ERROR",
+];
+
+pub async fn list(args: &OperationsListArgs) -> GResult<()> {
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        config,
+        ..
+    } = api_clients(&args.api).await?;
+    let project_id = resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+    let instance =
+        resolve_instance(&sql_admin, &config, &project_id, &args.instance, &args.api).await?;
+    let operations = sql_admin
+        .fetch_operations(&project_id, &instance.name, args.limit)
+        .await?;
+
+    let rows: Vec<_> = operations.iter().map(operation_fields).collect();
+    match args.output {
+        OutputFormat::Table => {
+            // Unfinished operations are what blocks other changes on the instance
+            let highlighted: Vec<_> = operations.iter().map(is_in_progress).collect();
+            print_table(OPERATION_HEADERS, &rows, &highlighted);
+        }
+        OutputFormat::Json => print_json(&operations)?,
+        OutputFormat::Csv => print_csv(OPERATION_HEADERS, &rows),
+    }
+
+    return Ok(());
+}
+
+pub async fn describe(args: &OperationsDescribeArgs) -> GResult<()> {
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        config,
+        ..
+    } = api_clients(&args.api).await?;
+    let project_id = resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+    let operation = sql_admin.fetch_operation(&project_id, &args.id).await?;
+
+    let fields = operation_fields(&operation);
+    match args.output {
+        OutputFormat::Table => {
+            let width = OPERATION_HEADERS
+                .map(str::len)
+                .into_iter()
+                .max()
+                .unwrap_or(0);
+            for (header, field) in OPERATION_HEADERS.iter().zip(&fields) {
+                println!(
+                    "{}  {}",
+                    style(format!("{:<width$}", header, width = width)).bold(),
+                    field
+                );
+            }
+        }
+        OutputFormat::Json => print_json(&operation)?,
+        OutputFormat::Csv => print_csv(OPERATION_HEADERS, &[fields]),
+    }
+
+    return Ok(());
+}
+
+pub async fn wait(args: &OperationsWaitArgs) -> GResult<()> {
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        config,
+        ..
+    } = api_clients(&args.api).await?;
+    let project_id = resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+    let operation = sql_admin.fetch_operation(&project_id, &args.id).await?;
+
+    let wait_args = WaitArgs {
+        timeout: args.timeout,
+        no_wait: false,
+    };
+    return wait_for_operation(&sql_admin, &config, &project_id, &operation, &wait_args).await;
+}
+
+pub async fn cancel(args: &OperationsCancelArgs) -> GResult<()> {
+    let ApiClients {
+        sql_admin,
+        resource_manager,
+        config,
+        ..
+    } = api_clients(&args.api).await?;
+    let project_id = resolve_project(&resource_manager, &config, &args.project, &args.api).await?;
+    let operation = sql_admin.fetch_operation(&project_id, &args.id).await?;
+    if !is_in_progress(&operation) {
+        return Err(GError::InvalidInput(format!(
+            "Operation `{}` is already {}",
+            operation.name, operation.status
+        )));
+    }
+
+    let prompt = format!(
+        "Cancel {} operation {} on {}{}. Continue?",
+        style(operation.operation_type.as_deref().unwrap_or("unknown"))
+            .green()
+            .bold(),
+        style(&operation.name).green().bold(),
+        style(operation.target_id.as_deref().unwrap_or("unknown instance"))
+            .green()
+            .bold(),
+        impersonation_notice(sql_admin.credentials()),
+    );
+    let confirmation = args.yes || Confirm::new().with_prompt(prompt).interact()?;
+
+    if !confirmation {
+        return Err(GError::Aborted("Aborting...".into()));
+    }
+
+    sql_admin
+        .cancel_operation(&project_id, &operation.name)
+        .await?;
+
+    info!("Operation {} cancelled.", operation.name);
+
+    return Ok(());
+}
+
+fn is_in_progress(operation: &Operation) -> bool {
+    return matches!(
+        operation.status,
+        OperationStatus::Pending | OperationStatus::Running
+    );
+}
+
+fn operation_fields(operation: &Operation) -> [String; 8] {
+    return [
+        operation.name.clone(),
+        operation.target_id.clone().unwrap_or_default(),
+        operation.operation_type.clone().unwrap_or_default(),
+        operation.status.to_string(),
+        operation.user.clone().unwrap_or_default(),
+        operation.start_time.clone().unwrap_or_default(),
+        operation.end_time.clone().unwrap_or_default(),
+        operation.error_message().unwrap_or_default(),
+    ];
+}
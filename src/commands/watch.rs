@@ -0,0 +1,399 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{Read, Write},
+    path::PathBuf,
+    process,
+    time::Duration,
+};
+
+use tokio::time::sleep;
+
+use super::{
+    common::{api_clients, ApiClients},
+    network::{current_target_ips, target_value, update_saved_targets},
+};
+use crate::{
+    client::{Endpoints, SqlAdminClient},
+    config::{GConfig, DEFAULT_WATCH_INTERVAL_SECS, DEFAULT_WATCH_MAX_BACKOFF_SECS},
+    info,
+    models::{
+        cli::{NetworkWatchArgs, WaitArgs},
+        NetworkOperation, NetworkTargetUpdateStatus,
+    },
+    store::GStore,
+    utils, warn, GError, GResult,
+};
+
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(15);
+const UNIT_NAME: &str = "gsqueal-watch";
+
+pub async fn watch(args: &NetworkWatchArgs) -> GResult<()> {
+    if args.systemd_unit {
+        print!("{}", systemd_unit(args)?);
+        return Ok(());
+    }
+
+    let ApiClients {
+        sql_admin,
+        endpoints,
+        config,
+        ..
+    } = api_clients(&args.api).await?;
+    let interval = Duration::from_secs(
+        args.interval
+            .or(config.network.watch_interval_secs)
+            .unwrap_or(DEFAULT_WATCH_INTERVAL_SECS),
+    );
+    let max_backoff = Duration::from_secs(
+        args.max_backoff
+            .or(config.network.watch_max_backoff_secs)
+            .unwrap_or(DEFAULT_WATCH_MAX_BACKOFF_SECS),
+    );
+    // Fail on unknown aliases right away instead of retrying them forever
+    let targets = saved_targets(&args.targets).await?;
+
+    let _lock = WatchLock::acquire()?;
+
+    info!(
+        "Watching {} saved targets, checking your IP every {}s",
+        targets.len(),
+        interval.as_secs()
+    );
+
+    tokio::select! {
+        result = watch_loop(&sql_admin, &config, &endpoints, args, interval, max_backoff) => result,
+        _ = shutdown_signal() => {
+            info!("Stopping watcher");
+            Ok(())
+        }
+    }
+}
+
+async fn watch_loop(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    endpoints: &Endpoints,
+    args: &NetworkWatchArgs,
+    interval: Duration,
+    max_backoff: Duration,
+) -> GResult<()> {
+    let mut synced = HashMap::new();
+    let mut failures = 0;
+
+    loop {
+        let result = sync(sql_admin, config, endpoints, args, &mut synced).await;
+        if args.once {
+            return result;
+        }
+
+        let delay = match result {
+            Ok(()) => {
+                failures = 0;
+                interval
+            }
+            Err(err) => {
+                failures += 1;
+                let delay = utils::jitter(
+                    RETRY_BASE_DELAY
+                        .saturating_mul(2_u32.saturating_pow(failures - 1))
+                        .min(max_backoff),
+                );
+                warn!(
+                    "{}, retrying in {:.0}s (failure {})",
+                    err,
+                    delay.as_secs_f64(),
+                    failures
+                );
+                delay
+            }
+        };
+        sleep(delay).await;
+    }
+}
+
+/// Runs the saved update flow for every target whose value differs from the one last synced to
+/// it, so targets saved or changed while watching are synced without waiting for a new IP.
+/// `synced` holds each alias's target and value as of its last successful update.
+async fn sync(
+    sql_admin: &SqlAdminClient,
+    config: &GConfig,
+    endpoints: &Endpoints,
+    args: &NetworkWatchArgs,
+    synced: &mut HashMap<String, (NetworkOperation, String)>,
+) -> GResult<()> {
+    // Re-read the store so targets saved or removed while watching are picked up
+    let targets = saved_targets(&args.targets).await?;
+    synced.retain(|alias, _| targets.contains_key(alias));
+    let current_ips = current_target_ips(sql_admin, config, endpoints, &targets, &args.ip).await?;
+
+    let mut pending = BTreeMap::new();
+    for (alias, target) in &targets {
+        let value = target_value(config, alias, target, &current_ips, &args.ip, args.prefix)?;
+        let synced_value = synced
+            .get(alias)
+            .filter(|(synced_target, _)| synced_target == target)
+            .map(|(_, synced_value)| synced_value);
+        if synced_value != Some(&value) {
+            pending.insert(alias.clone(), target.clone());
+        }
+    }
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let mut ips: Vec<_> = current_ips.values().collect();
+    ips.sort();
+    info!(
+        "Current IP is {}, syncing {} of {} saved targets",
+        ips.iter()
+            .map(|ip| ip.to_string())
+            .collect::<Vec<_>>()
+            .join(" and "),
+        pending.len(),
+        targets.len()
+    );
+
+    let wait_args = WaitArgs {
+        timeout: None,
+        no_wait: false,
+    };
+    let items = update_saved_targets(
+        sql_admin,
+        config,
+        &pending,
+        &current_ips,
+        &args.ip,
+        args.prefix,
+        &wait_args,
+    )
+    .await?;
+
+    for item in &items {
+        let detail = item.detail.as_deref().unwrap_or_default();
+        match item.status {
+            NetworkTargetUpdateStatus::Failed => warn!(
+                "{}: failed to set {} on {}/{}: {}",
+                item.alias, item.network, item.project, item.instance, detail
+            ),
+            status => info!(
+                "{}: {} {} on {}/{} {}",
+                item.alias, status, item.network, item.project, item.instance, item.value
+            ),
+        }
+    }
+
+    for item in &items {
+        if item.status != NetworkTargetUpdateStatus::Failed {
+            synced.insert(
+                item.alias.clone(),
+                (pending[&item.alias].clone(), item.value.clone()),
+            );
+        }
+    }
+
+    let failed = items
+        .iter()
+        .filter(|item| item.status == NetworkTargetUpdateStatus::Failed)
+        .count();
+    if failed > 0 {
+        return Err(GError::OperationFailed(format!(
+            "{} of {} saved targets failed to update",
+            failed,
+            items.len()
+        )));
+    }
+
+    return Ok(());
+}
+
+/// The saved targets matching `aliases`, or all of them when none are given
+async fn saved_targets(aliases: &[String]) -> GResult<BTreeMap<String, NetworkOperation>> {
+    let targets = GStore::get().await?.network.targets;
+    if let Some(alias) = aliases.iter().find(|alias| !targets.contains_key(*alias)) {
+        return Err(GError::NotFound(format!(
+            "Cannot find a saved target named `{}`, see `gsqueal network targets list`",
+            alias
+        )));
+    }
+
+    let targets: BTreeMap<_, _> = targets
+        .into_iter()
+        .filter(|(alias, _)| aliases.is_empty() || aliases.contains(alias))
+        .collect();
+    if targets.is_empty() {
+        return Err(GError::NotFound(
+            "Cannot find any saved targets, save one with `gsqueal network save <alias>`".into(),
+        ));
+    }
+
+    return Ok(targets);
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        // systemd stops services with SIGTERM
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Advisory lock that keeps two watchers from fighting over the same targets. The OS releases it
+/// when the file is closed, even after a crash, and the PID in it is only informational.
+struct WatchLock {
+    file: File,
+}
+
+impl WatchLock {
+    fn path() -> GResult<PathBuf> {
+        let path = dirs::data_dir()
+            .ok_or_else(|| GError::NotFound("Unable to get data directory".into()))?
+            .join("gsqueal/watch")
+            .with_extension("pid");
+
+        return Ok(path);
+    }
+    fn acquire() -> GResult<Self> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Never truncate or remove the file before holding the lock, another watcher may own it
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut pid = String::new();
+                file.read_to_string(&mut pid)?;
+                let owner = match pid.trim() {
+                    "" => String::new(),
+                    pid => format!(" with PID {}", pid),
+                };
+                return Err(GError::Locked(format!(
+                    "Another watcher is already running{}, lock file `{}`",
+                    owner,
+                    path.display()
+                )));
+            }
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+
+        file.set_len(0)?;
+        write!(file, "{}", process::id())?;
+
+        return Ok(Self { file });
+    }
+}
+
+impl Drop for WatchLock {
+    fn drop(&mut self) {
+        // Keep the file itself, removing it would let a watcher lock a file no other can see
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// A systemd user unit running this watcher with the same options
+fn systemd_unit(args: &NetworkWatchArgs) -> GResult<String> {
+    let executable = std::env::current_exe()?;
+    let mut command = vec![
+        executable.display().to_string(),
+        "network".into(),
+        "watch".into(),
+    ];
+    for alias in &args.targets {
+        command.extend(["--target".into(), alias.clone()]);
+    }
+    if let Some(interval) = args.interval {
+        command.extend(["--interval".into(), interval.to_string()]);
+    }
+    if let Some(max_backoff) = args.max_backoff {
+        command.extend(["--max-backoff".into(), max_backoff.to_string()]);
+    }
+    if let Some(prefix) = args.prefix {
+        command.extend(["--prefix".into(), prefix.to_string()]);
+    }
+    if args.ip.ipv6 {
+        command.push("--ipv6".into());
+    }
+    if let Some(ip) = args.ip.ip {
+        command.extend(["--ip".into(), ip.to_string()]);
+    }
+    if args.ip.consensus {
+        command.push("--consensus".into());
+    }
+    if let Some(page_size) = args.api.page_size {
+        command.extend(["--page-size".into(), page_size.to_string()]);
+    }
+    if let Some(project_filter) = &args.api.project_filter {
+        command.extend(["--project-filter".into(), project_filter.clone()]);
+    }
+    if let Some(service_account) = &args.api.impersonate_service_account {
+        command.extend([
+            "--impersonate-service-account".into(),
+            service_account.clone(),
+        ]);
+    }
+    if let Some(max_attempts) = args.api.max_attempts {
+        command.extend(["--max-attempts".into(), max_attempts.to_string()]);
+    }
+    for endpoint in &args.api.api_endpoint {
+        command.extend(["--api-endpoint".into(), endpoint.clone()]);
+    }
+    let exec_start = command
+        .iter()
+        .map(|arg| systemd_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    return Ok(format!(
+        "# Save as ~/.config/systemd/user/{name}.service, then run:
+#   systemctl --user daemon-reload
+#   systemctl --user enable --now {name}
+[Unit]
+Description=Keep Cloud SQL authorized networks in sync with the current IP
+
+[Service]
+ExecStart={exec_start}
+Restart=on-failure
+RestartSec=30
+
+[Install]
+WantedBy=default.target
+",
+        name = UNIT_NAME,
+        exec_start = exec_start,
+    ));
+}
+
+/// Quotes an `ExecStart` argument, `%` starts a specifier in systemd units
+fn systemd_quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\'))
+    {
+        return format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+
+    return arg;
+}
@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{GError, GResult};
+
+pub const DEFAULT_PREFIX: u8 = 24;
+pub const DEFAULT_IPV6_PREFIX: u8 = 64;
+pub const DEFAULT_OPERATION_TIMEOUT_SECS: u64 = 600;
+pub const DEFAULT_WATCH_INTERVAL_SECS: u64 = 300;
+pub const DEFAULT_WATCH_MAX_BACKOFF_SECS: u64 = 1800;
+/// Matches active projects under organizations, folders or without any parent
+pub const DEFAULT_PROJECT_FILTER: &str = "lifecycleState:ACTIVE";
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct GConfig {
+    pub network: NetworkConfig,
+    pub api: ApiConfig,
+    pub projects: ProjectsConfig,
+    pub auth: AuthConfig,
+    pub http: HttpConfig,
+    pub endpoints: EndpointsConfig,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Default prefix length used for the current IPv4 address's CIDR notation
+    pub prefix: Option<u8>,
+    /// Default prefix length used for the current IPv6 address's CIDR notation
+    pub ipv6_prefix: Option<u8>,
+    /// HTTP(S) URLs or `stun:host:port` servers used to discover the current IP, in order
+    pub ip_providers: Vec<String>,
+    /// Timeout for each IP provider in seconds
+    pub ip_timeout_secs: Option<u64>,
+    /// Requires two IP providers to agree on the current IP
+    pub ip_consensus: bool,
+    /// Seconds between IP checks of `network watch`
+    pub watch_interval_secs: Option<u64>,
+    /// Longest delay in seconds between retries of `network watch` after failures
+    pub watch_max_backoff_secs: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Number of results requested per page when listing projects and instances
+    pub page_size: Option<u32>,
+    /// Seconds to wait for an operation to finish
+    pub operation_timeout_secs: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ProjectsConfig {
+    /// Resource Manager filter used to discover projects
+    pub filter: Option<String>,
+    /// Fixed list of project IDs to select from instead of discovering them
+    pub pinned: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Service account key or `authorized_user` credentials file, used instead of gcloud
+    pub credentials_file: Option<PathBuf>,
+    /// Persists access tokens next to the store so they're reused across invocations
+    pub persist_token_cache: bool,
+    /// Service account email to impersonate for every API call
+    pub impersonate_service_account: Option<String>,
+    /// Metadata server used on Compute Engine and GKE, `GCE_METADATA_HOST` takes precedence
+    pub metadata_host: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Timeout of each API request in seconds
+    pub timeout_secs: Option<u64>,
+    /// Timeout for establishing a connection in seconds
+    pub connect_timeout_secs: Option<u64>,
+    /// Proxy URL used for every API request, e.g. `http://proxy.internal:3128`
+    pub proxy: Option<String>,
+    /// Attempts per API request, transient failures are retried with exponential backoff
+    pub max_attempts: Option<u32>,
+    /// Overrides the default `gsqueal/<version>` user agent
+    pub user_agent: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct EndpointsConfig {
+    /// Cloud SQL Admin API base URL, e.g. a regional endpoint or a local emulator
+    pub sqladmin: Option<String>,
+    /// Cloud Resource Manager API base URL
+    pub cloudresourcemanager: Option<String>,
+    /// IAM Credentials API base URL, used for service account impersonation
+    pub iamcredentials: Option<String>,
+    /// URL returning the current IP as plain text or JSON, replaces the IP providers
+    pub ip: Option<String>,
+}
+
+impl GConfig {
+    fn path() -> GResult<PathBuf> {
+        let path = dirs::config_dir()
+            .ok_or_else(|| GError::NotFound("Unable to get config directory".into()))?
+            .join("gsqueal/config")
+            .with_extension("json");
+
+        return Ok(path);
+    }
+    pub async fn get() -> GResult<Self> {
+        let config_path = Self::path()?;
+
+        if config_path.exists() {
+            let content = fs::read_to_string(&config_path).await?;
+            let config: Self =
+                serde_json::from_str(&content).map_err(|err| GError::StoreCorrupted {
+                    path: config_path,
+                    message: err.to_string(),
+                })?;
+
+            return Ok(config);
+        } else {
+            let config = Self::default();
+            config.set().await?;
+
+            return Ok(config);
+        }
+    }
+    pub async fn set(&self) -> GResult<()> {
+        let config_path = Self::path()?;
+
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let serialized_data = serde_json::to_string_pretty(self)
+            .map_err(|err| GError::Unknown(format!("Failed to serialize config: {}", err)))?;
+        fs::write(config_path, serialized_data).await?;
+
+        return Ok(());
+    }
+}
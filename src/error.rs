@@ -1,11 +1,139 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
+use crate::models::google::ApiError;
+
 #[derive(Error, Debug)]
 pub enum GError {
     #[error("{0}")]
     Unknown(String),
     #[error("{0}")]
     Timeout(String),
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Request failed with HTTP status {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+    #[error("{}", api_error_message(.0))]
+    Api(Box<ApiError>),
+    #[error("Failed to parse response as JSON: {source}, response: {body}")]
+    Json {
+        source: serde_json::Error,
+        body: String,
+    },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("`{}` is corrupted: {message}", path.display())]
+    StoreCorrupted { path: PathBuf, message: String },
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    Aborted(String),
+    #[error("{0}")]
+    OperationFailed(String),
+    /// Another process holds a lock this command needs
+    #[error("{0}")]
+    Locked(String),
+}
+
+impl GError {
+    /// Process exit code for this error, so wrapper scripts can react to specific failures
+    pub fn exit_code(&self) -> i32 {
+        return match self {
+            GError::Unknown(_) => 1,
+            GError::Timeout(_) => 2,
+            GError::Auth(_) => 3,
+            GError::Http(_) => 4,
+            GError::HttpStatus { .. } => 5,
+            GError::Api(_) => 6,
+            GError::Json { .. } => 7,
+            GError::Io(_) => 8,
+            GError::StoreCorrupted { .. } => 9,
+            GError::NotFound(_) => 10,
+            GError::InvalidInput(_) => 11,
+            GError::Aborted(_) => 12,
+            GError::OperationFailed(_) => 13,
+            GError::Locked(_) => 14,
+        };
+    }
+}
+
+impl From<dialoguer::Error> for GError {
+    fn from(err: dialoguer::Error) -> Self {
+        return match err {
+            dialoguer::Error::IO(err) => GError::Io(err),
+        };
+    }
+}
+
+fn api_error_message(error: &ApiError) -> String {
+    let mut message = format!("{} ({} {})", error.message, error.code, error.status);
+    if let Some(hint) = api_error_hint(error) {
+        message.push_str(&format!("\n  hint: {}", hint));
+    }
+
+    return message;
+}
+
+/// Actionable suggestion for the common Admin API and Resource Manager failures
+fn api_error_hint(error: &ApiError) -> Option<String> {
+    let reasons: Vec<&str> = error
+        .errors
+        .iter()
+        .map(|item| item.reason.as_str())
+        .chain(
+            error
+                .details
+                .iter()
+                .filter_map(|detail| detail.reason.as_deref()),
+        )
+        .collect();
+
+    if reasons.contains(&"SERVICE_DISABLED") || reasons.contains(&"accessNotConfigured") {
+        let metadata = error.details.iter().map(|detail| &detail.metadata);
+        let hint = match metadata.clone().find_map(|metadata| metadata.get("activationUrl")) {
+            Some(url) => format!("The API is not enabled for this project, enable it at {}", url),
+            None => format!(
+                "The API is not enabled for this project, enable it with `gcloud services enable {}`",
+                metadata
+                    .clone()
+                    .find_map(|metadata| metadata.get("service"))
+                    .map_or("sqladmin.googleapis.com", String::as_str)
+            ),
+        };
+        return Some(hint);
+    }
+    if reasons.contains(&"operationInProgress") || error.code == 409 {
+        return Some(
+            "Another operation is already in progress on the instance, find it with `gsqueal \
+             operations list` and wait for it to finish or cancel it"
+                .into(),
+        );
+    }
+
+    let hint = match error.code {
+        401 => {
+            "Your credentials are invalid or expired, renew them where they come from: \
+             `gcloud auth login` for gcloud, `gcloud auth application-default login` for \
+             application default credentials, a new key for the service account key file, or \
+             the attached service account on Compute Engine and GKE"
+        }
+        403 => {
+            "Make sure your account has the `cloudsql.instances.get` and \
+             `cloudsql.instances.update` permissions on the project, e.g. through the Cloud SQL \
+             Admin role"
+        }
+        404 => "Check that the project ID and instance name are correct",
+        412 => "The instance was modified by someone else in the meantime, try again",
+        429 => "API quota exceeded, wait a moment and try again",
+        _ => return None,
+    };
+
+    return Some(hint.into());
 }
 
 pub type GResult<T> = core::result::Result<T, GError>;
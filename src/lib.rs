@@ -1,11 +1,17 @@
+pub mod auth;
+pub mod client;
+pub(crate) mod config;
 pub(crate) mod error;
-pub(crate) mod log;
+pub mod log;
 pub(crate) mod store;
 pub(crate) mod utils;
 
 pub use error::*;
 
 pub mod commands {
+    pub(crate) mod common;
     pub mod network;
+    pub mod operations;
+    pub mod watch;
 }
 pub mod models;
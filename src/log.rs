@@ -8,13 +8,17 @@ pub enum LogLevel {
     Error,
 }
 
+/// Info lines go to stdout, warnings and errors to stderr so they never mix with JSON or CSV output
 pub fn message(level: LogLevel, message: Arguments) {
     let log_level = match level {
         LogLevel::Info => style("[info]").green().bold(),
-        LogLevel::Warn => style("[warn]").yellow().bold(),
-        LogLevel::Error => style("[error]").red().bold(),
+        LogLevel::Warn => style("[warn]").for_stderr().yellow().bold(),
+        LogLevel::Error => style("[error]").for_stderr().red().bold(),
     };
-    println!("{} {}", log_level, message);
+    match level {
+        LogLevel::Info => println!("{} {}", log_level, message),
+        LogLevel::Warn | LogLevel::Error => eprintln!("{} {}", log_level, message),
+    }
 }
 
 #[macro_export]
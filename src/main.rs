@@ -1,19 +1,50 @@
 use clap::Parser;
-use gsqueal::commands::network;
-use gsqueal::models::cli::{Cli, NetworkCommands, RootCommands};
+use gsqueal::commands::{network, operations, watch};
+use gsqueal::models::cli::{
+    Cli, NetworkCommands, NetworkTargetsCommands, OperationsCommands, RootCommands,
+};
+use gsqueal::{error, warn, GError};
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
-    match &cli.command {
+    let result = match &cli.command {
         RootCommands::Network { command } => match command {
-            Some(NetworkCommands::Update { repeat_last }) => {
-                network::update(repeat_last).await;
-            }
-            None => {
-                panic!("No network subcommand provided. Use --help to see available options.")
-            }
+            Some(NetworkCommands::Update(args)) => network::update(args).await,
+            Some(NetworkCommands::Add(args)) => network::add(args).await,
+            Some(NetworkCommands::Remove(args)) => network::remove(args).await,
+            Some(NetworkCommands::List(args)) => network::list(args).await,
+            Some(NetworkCommands::Save(args)) => network::save(args).await,
+            Some(NetworkCommands::Watch(args)) => watch::watch(args).await,
+            Some(NetworkCommands::Targets { command }) => match command {
+                Some(NetworkTargetsCommands::List(args)) => network::list_targets(args).await,
+                Some(NetworkTargetsCommands::Rm(args)) => network::remove_targets(args).await,
+                None => Err(GError::InvalidInput(
+                    "No network targets subcommand provided. Use --help to see available options."
+                        .into(),
+                )),
+            },
+            None => Err(GError::InvalidInput(
+                "No network subcommand provided. Use --help to see available options.".into(),
+            )),
         },
+        RootCommands::Operations { command } => match command {
+            Some(OperationsCommands::List(args)) => operations::list(args).await,
+            Some(OperationsCommands::Describe(args)) => operations::describe(args).await,
+            Some(OperationsCommands::Wait(args)) => operations::wait(args).await,
+            Some(OperationsCommands::Cancel(args)) => operations::cancel(args).await,
+            None => Err(GError::InvalidInput(
+                "No operations subcommand provided. Use --help to see available options.".into(),
+            )),
+        },
+    };
+
+    if let Err(err) = result {
+        match &err {
+            GError::Aborted(message) => warn!("{}", message),
+            err => error!("{}", err),
+        }
+        std::process::exit(err.exit_code());
     }
 }
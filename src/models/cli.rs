@@ -1,4 +1,6 @@
-use clap::{command, Parser, Subcommand};
+use std::net::IpAddr;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -14,14 +16,402 @@ pub enum RootCommands {
         #[command(subcommand)]
         command: Option<NetworkCommands>,
     },
+    /// Inspect, wait for and cancel CloudSQL instance operations
+    Operations {
+        #[command(subcommand)]
+        command: Option<OperationsCommands>,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum NetworkCommands {
-    /// Updates the authorized network with your current IP address in /24 CIDR notation
-    Update {
-        /// Repeats the last update operation using your current IP
-        #[arg(short, long)]
-        repeat_last: bool,
+    /// Updates the authorized network with your current IP address in CIDR notation
+    Update(NetworkUpdateArgs),
+    /// Adds a new authorized network, defaults to your current IP address in CIDR notation
+    Add(NetworkAddArgs),
+    /// Removes authorized networks by selection, name pattern or expiration
+    Remove(NetworkRemoveArgs),
+    /// Lists the authorized networks of an instance or of every instance in a project
+    List(NetworkListArgs),
+    /// Saves an instance's authorized network as a named target for `network update --target`
+    Save(NetworkSaveArgs),
+    /// Keeps saved targets in sync with your current IP, checking it periodically
+    Watch(NetworkWatchArgs),
+    /// Manage saved network targets
+    Targets {
+        #[command(subcommand)]
+        command: Option<NetworkTargetsCommands>,
     },
 }
+
+#[derive(Subcommand)]
+pub enum NetworkTargetsCommands {
+    /// Lists the saved network targets
+    List(NetworkTargetsListArgs),
+    /// Removes saved network targets
+    Rm(NetworkTargetsRemoveArgs),
+}
+
+#[derive(Subcommand)]
+pub enum OperationsCommands {
+    /// Lists the most recent operations of an instance
+    List(OperationsListArgs),
+    /// Shows the details of an operation
+    Describe(OperationsDescribeArgs),
+    /// Waits for an operation to finish
+    Wait(OperationsWaitArgs),
+    /// Cancels a pending or running operation
+    Cancel(OperationsCancelArgs),
+}
+
+#[derive(Args)]
+pub struct NetworkUpdateArgs {
+    /// Repeats the last update operation using your current IP
+    #[arg(short, long, conflicts_with_all = ["project", "instance", "network"])]
+    pub repeat_last: bool,
+
+    /// Updates a target saved with `network save` using your current IP
+    #[arg(short, long, value_name = "ALIAS", conflicts_with_all = ["repeat_last", "project", "instance", "network"])]
+    pub target: Option<String>,
+
+    /// Updates every saved target using your current IP, one operation per instance in parallel
+    #[arg(long, conflicts_with_all = ["repeat_last", "target", "project", "instance", "network"])]
+    pub all_saved: bool,
+
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Instance name to use instead of prompting for one
+    #[arg(short, long)]
+    pub instance: Option<String>,
+
+    /// Authorized network name to use instead of prompting for one
+    #[arg(short, long)]
+    pub network: Option<String>,
+
+    /// Prefix length of your current IP's CIDR notation, defaults to the configured prefix or 24 for IPv4 and 64 for IPv6
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=128))]
+    pub prefix: Option<u8>,
+
+    #[command(flatten)]
+    pub ip: IpArgs,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    #[command(flatten)]
+    pub wait: WaitArgs,
+
+    /// Skips the confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Args)]
+pub struct NetworkAddArgs {
+    /// Name of the new authorized network
+    pub name: String,
+
+    /// Value of the new authorized network in CIDR notation, defaults to your current IP
+    #[arg(short, long)]
+    pub value: Option<String>,
+
+    /// Expiration time of the new authorized network in RFC 3339 format
+    #[arg(short, long)]
+    pub expiration_time: Option<String>,
+
+    /// Prefix length of your current IP's CIDR notation, defaults to the configured prefix or 24 for IPv4 and 64 for IPv6
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=128))]
+    pub prefix: Option<u8>,
+
+    #[command(flatten)]
+    pub ip: IpArgs,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    #[command(flatten)]
+    pub wait: WaitArgs,
+
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Instance name to use instead of prompting for one
+    #[arg(short, long)]
+    pub instance: Option<String>,
+
+    /// Skips the confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Args)]
+pub struct NetworkRemoveArgs {
+    /// Names or glob patterns of the authorized networks to remove, prompts for a selection if omitted
+    #[arg(conflicts_with = "expired")]
+    pub patterns: Vec<String>,
+
+    /// Removes every authorized network whose expiration time is in the past
+    #[arg(long)]
+    pub expired: bool,
+
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Instance name to use instead of prompting for one
+    #[arg(short, long)]
+    pub instance: Option<String>,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    #[command(flatten)]
+    pub wait: WaitArgs,
+
+    /// Skips the confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Args)]
+pub struct NetworkListArgs {
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Instance name to use instead of prompting for one
+    #[arg(short, long, conflicts_with = "all_instances")]
+    pub instance: Option<String>,
+
+    /// Lists the authorized networks of every instance in the project
+    #[arg(short, long)]
+    pub all_instances: bool,
+
+    #[command(flatten)]
+    pub ip: IpArgs,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct NetworkSaveArgs {
+    /// Name to save the target under
+    pub alias: String,
+
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Instance name to use instead of prompting for one
+    #[arg(short, long)]
+    pub instance: Option<String>,
+
+    /// Authorized network name to use instead of prompting for one
+    #[arg(short, long)]
+    pub network: Option<String>,
+
+    /// Prefix length of your current IP's CIDR notation, defaults to the configured prefix when updating
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=128))]
+    pub prefix: Option<u8>,
+
+    /// Uses your current IPv6 address instead of IPv4 when updating
+    #[arg(short = '6', long)]
+    pub ipv6: bool,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    /// Overwrites an existing target with the same name
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct NetworkWatchArgs {
+    /// Saved targets to keep in sync, defaults to all of them
+    #[arg(short, long = "target", value_name = "ALIAS")]
+    pub targets: Vec<String>,
+
+    /// Seconds between IP checks, defaults to the configured interval or 300
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: Option<u64>,
+
+    /// Longest delay in seconds between retries after failures, defaults to the configured one or 1800
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_backoff: Option<u64>,
+
+    /// Prefix length of your current IP's CIDR notation, overrides the saved prefixes
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=128))]
+    pub prefix: Option<u8>,
+
+    /// Checks and syncs once, then exits
+    #[arg(long, conflicts_with = "systemd_unit")]
+    pub once: bool,
+
+    /// Prints a systemd user unit that runs this watcher with the same options, then exits
+    #[arg(long)]
+    pub systemd_unit: bool,
+
+    #[command(flatten)]
+    pub ip: IpArgs,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+}
+
+#[derive(Args)]
+pub struct NetworkTargetsListArgs {
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct NetworkTargetsRemoveArgs {
+    /// Names of the targets to remove
+    #[arg(required = true)]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct OperationsListArgs {
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Instance name to use instead of prompting for one
+    #[arg(short, long)]
+    pub instance: Option<String>,
+
+    /// Maximum number of operations to list
+    #[arg(short, long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub limit: u32,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct OperationsDescribeArgs {
+    /// Operation ID
+    pub id: String,
+
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct OperationsWaitArgs {
+    /// Operation ID
+    pub id: String,
+
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    /// Seconds to wait for the operation to finish, defaults to the configured timeout or 600
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<u64>,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+}
+
+#[derive(Args)]
+pub struct OperationsCancelArgs {
+    /// Operation ID
+    pub id: String,
+
+    /// Project ID to use instead of prompting for one
+    #[arg(short, long)]
+    pub project: Option<String>,
+
+    #[command(flatten)]
+    pub api: ApiArgs,
+
+    /// Skips the confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct IpArgs {
+    /// Uses your current IPv6 address instead of IPv4
+    #[arg(short = '6', long)]
+    pub ipv6: bool,
+
+    /// Uses this IP address instead of discovering your current one
+    #[arg(long)]
+    pub ip: Option<IpAddr>,
+
+    /// Requires two IP providers to agree on your current IP
+    #[arg(long, conflicts_with = "ip")]
+    pub consensus: bool,
+}
+
+impl IpArgs {
+    pub fn is_ipv6(&self) -> bool {
+        return self.ipv6 || self.ip.is_some_and(|ip| ip.is_ipv6());
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct ApiArgs {
+    /// Number of results requested per page when listing projects and instances
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub page_size: Option<u32>,
+
+    /// Resource Manager filter used to discover projects, e.g. `parent.id:123456789`
+    #[arg(long)]
+    pub project_filter: Option<String>,
+
+    /// Service account email to impersonate, defaults to the configured one
+    #[arg(long, value_name = "EMAIL")]
+    pub impersonate_service_account: Option<String>,
+
+    /// Attempts per API request, transient failures are retried with exponential backoff
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_attempts: Option<u32>,
+
+    /// Overrides a service's base URL, where SERVICE is `sqladmin`, `cloudresourcemanager`, `iamcredentials` or `ip`
+    #[arg(long, value_name = "SERVICE=URL")]
+    pub api_endpoint: Vec<String>,
+}
+
+#[derive(Args, Clone)]
+pub struct WaitArgs {
+    /// Seconds to wait for the operation to finish, defaults to the configured timeout or 600
+    #[arg(long, value_name = "SECS", conflicts_with = "no_wait")]
+    pub timeout: Option<u64>,
+
+    /// Prints the operation ID and returns without waiting for the operation to finish
+    #[arg(long)]
+    pub no_wait: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
@@ -1,9 +1,15 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProjectsResponse {
+    #[serde(default)]
     pub projects: Vec<Project>,
+    pub next_page_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -16,7 +22,9 @@ pub struct Project {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InstancesResponse {
+    #[serde(default)]
     pub items: Vec<Instance>,
+    pub next_page_token: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -24,19 +32,28 @@ pub struct InstancesResponse {
 pub struct Instance {
     pub project: String,
     pub name: String,
+    pub etag: Option<String>,
     pub settings: Settings,
+    /// Fields gsqueal doesn't model, kept so they survive a round trip
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Settings {
     pub ip_configuration: IpConfiguration,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IpConfiguration {
+    #[serde(default)]
     pub authorized_networks: Vec<AuthorizedNetwork>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -46,6 +63,8 @@ pub struct AuthorizedNetwork {
     pub value: String,
     pub kind: String,
     pub expiration_time: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -53,13 +72,223 @@ pub struct AuthorizedNetwork {
 pub struct Operation {
     pub name: String,
     pub status: OperationStatus,
+    pub operation_type: Option<String>,
+    pub user: Option<String>,
+    pub target_id: Option<String>,
+    pub target_project: Option<String>,
+    pub insert_time: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub error: Option<OperationErrors>,
+}
+
+impl Operation {
+    /// Reasons reported in `error.errors[]`, joined for display
+    pub fn error_message(&self) -> Option<String> {
+        let errors = &self.error.as_ref()?.errors;
+        if errors.is_empty() {
+            return None;
+        }
+
+        return Some(
+            errors
+                .iter()
+                .map(|error| match &error.message {
+                    Some(message) => format!("{} ({})", message, error.code),
+                    None => error.code.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+        );
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationErrors {
+    #[serde(default)]
+    pub errors: Vec<OperationError>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationError {
+    pub code: String,
+    pub message: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum OperationStatus {
+    #[serde(rename = "SQL_OPERATION_STATUS_UNSPECIFIED", alias = "UNSPECIFIED")]
     Unspecified,
     Pending,
     Running,
     Done,
 }
+
+impl fmt::Display for OperationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            Self::Unspecified => "UNSPECIFIED",
+            Self::Pending => "PENDING",
+            Self::Running => "RUNNING",
+            Self::Done => "DONE",
+        };
+        return write!(f, "{}", status);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationsResponse {
+    #[serde(default)]
+    pub items: Vec<Operation>,
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    pub error: ApiError,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    pub code: u16,
+    pub message: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub errors: Vec<ApiErrorItem>,
+    #[serde(default)]
+    pub details: Vec<ApiErrorDetail>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiErrorItem {
+    #[serde(default)]
+    pub domain: String,
+    #[serde(default)]
+    pub reason: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiErrorDetail {
+    #[serde(rename = "@type")]
+    pub type_url: String,
+    pub reason: Option<String>,
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CredentialsFile {
+    ServiceAccount(ServiceAccountKey),
+    AuthorizedUser(AuthorizedUserCredentials),
+}
+
+/// Shown instead of secrets in `Debug` output
+pub const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key: String,
+    pub private_key_id: Option<String>,
+    pub token_uri: Option<String>,
+}
+
+impl fmt::Debug for ServiceAccountKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("ServiceAccountKey")
+            .field("client_email", &self.client_email)
+            .field("private_key", &REDACTED)
+            .field("private_key_id", &self.private_key_id)
+            .field("token_uri", &self.token_uri)
+            .finish();
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct AuthorizedUserCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+    pub token_uri: Option<String>,
+}
+
+impl fmt::Debug for AuthorizedUserCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("AuthorizedUserCredentials")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &REDACTED)
+            .field("refresh_token", &REDACTED)
+            .field("token_uri", &self.token_uri)
+            .finish();
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JwtClaims {
+    pub iss: String,
+    pub scope: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub expires_in: Option<i64>,
+}
+
+impl fmt::Debug for TokenResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("TokenResponse")
+            .field("access_token", &REDACTED)
+            .field("expires_in", &self.expires_in)
+            .finish();
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateAccessTokenRequest {
+    pub scope: Vec<String>,
+    pub lifetime: String,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateAccessTokenResponse {
+    pub access_token: String,
+    pub expire_time: DateTime<Utc>,
+}
+
+impl fmt::Debug for GenerateAccessTokenResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("GenerateAccessTokenResponse")
+            .field("access_token", &REDACTED)
+            .field("expire_time", &self.expire_time)
+            .finish();
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenErrorResponse {
+    pub error: String,
+    pub error_description: Option<String>,
+}
@@ -1,25 +1,55 @@
-use google::Settings;
+use std::{collections::BTreeMap, fmt};
+
+use google::AuthorizedNetwork;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 pub mod cli;
 pub mod google;
 
+/// PATCH body carrying only the fields gsqueal changes, everything left out stays untouched
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InstanceUpdateDto {
-    pub settings: Settings,
+    /// Deprecated in favour of `settings.settingsVersion` but still sent for older API versions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    pub settings: SettingsUpdateDto,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsUpdateDto {
+    /// Settings version the change is based on, makes the update fail instead of overwriting
+    /// concurrent changes. Passed through as read since the API encodes it as an int64 string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings_version: Option<Value>,
+    pub ip_configuration: IpConfigurationUpdateDto,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpConfigurationUpdateDto {
+    pub authorized_networks: Vec<AuthorizedNetwork>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Network {
     pub last_operation: Option<NetworkOperation>,
+    /// Saved targets by alias
+    #[serde(default)]
+    pub targets: BTreeMap<String, NetworkOperation>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct NetworkOperation {
     pub project_id: String,
     pub instance_id: String,
     pub network_name: String,
+    #[serde(default)]
+    pub prefix: Option<u8>,
+    #[serde(default)]
+    pub ipv6: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -27,3 +57,62 @@ pub struct NetworkUpdateDto {
     pub name: String,
     pub value: String,
 }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkListItem {
+    pub project: String,
+    pub instance: String,
+    pub name: String,
+    pub value: String,
+    pub kind: String,
+    pub expiration_time: Option<String>,
+    /// `None` when the current IP couldn't be discovered
+    pub current_ip: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkTargetItem {
+    pub alias: String,
+    pub project: String,
+    pub instance: String,
+    pub network: String,
+    pub prefix: Option<u8>,
+    pub ipv6: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkTargetUpdateItem {
+    pub alias: String,
+    pub project: String,
+    pub instance: String,
+    pub network: String,
+    pub value: String,
+    pub status: NetworkTargetUpdateStatus,
+    /// Operation ID or failure reason
+    pub detail: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkTargetUpdateStatus {
+    Updated,
+    /// Operation started but not waited for
+    Started,
+    Skipped,
+    Failed,
+}
+
+impl fmt::Display for NetworkTargetUpdateStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            Self::Updated => "updated",
+            Self::Started => "started",
+            Self::Skipped => "skipped",
+            Self::Failed => "failed",
+        };
+        return write!(f, "{}", status);
+    }
+}
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use tokio::fs;
 
 use crate::models::Network;
+use crate::{GError, GResult};
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct GStore {
@@ -11,41 +12,44 @@ pub struct GStore {
 }
 
 impl GStore {
-    fn path() -> PathBuf {
-        return dirs::data_dir()
-            .expect("Unable to get data directory")
+    fn path() -> GResult<PathBuf> {
+        let path = dirs::data_dir()
+            .ok_or_else(|| GError::NotFound("Unable to get data directory".into()))?
             .join("gsqueal/store")
             .with_extension("json");
+
+        return Ok(path);
     }
-    pub async fn get() -> Self {
-        let store_path = Self::path();
+    pub async fn get() -> GResult<Self> {
+        let store_path = Self::path()?;
 
         if store_path.exists() {
-            let content = fs::read_to_string(store_path)
-                .await
-                .expect("Failed to read from store");
-            let store: Self = serde_json::from_str(&content).expect("Failed to deserialize data");
-
-            return store;
+            let content = fs::read_to_string(&store_path).await?;
+            let store: Self =
+                serde_json::from_str(&content).map_err(|err| GError::StoreCorrupted {
+                    path: store_path,
+                    message: err.to_string(),
+                })?;
+
+            return Ok(store);
         } else {
             let store = Self::default();
-            store.set().await;
+            store.set().await?;
 
-            return store;
+            return Ok(store);
         }
     }
-    pub async fn set(&self) {
-        let store_path = Self::path();
+    pub async fn set(&self) -> GResult<()> {
+        let store_path = Self::path()?;
 
         if let Some(parent) = store_path.parent() {
-            fs::create_dir_all(parent)
-                .await
-                .expect("Failed to create parent directories");
+            fs::create_dir_all(parent).await?;
         }
 
-        let serialized_data = serde_json::to_string_pretty(self).expect("Failed to serialize data");
-        fs::write(store_path, serialized_data)
-            .await
-            .expect("Failed to write to store");
+        let serialized_data = serde_json::to_string_pretty(self)
+            .map_err(|err| GError::Unknown(format!("Failed to serialize store: {}", err)))?;
+        fs::write(store_path, serialized_data).await?;
+
+        return Ok(());
     }
 }
@@ -1,221 +1,263 @@
-use reqwest::{header, Client};
-use std::{process::Command, time::Duration};
-use tokio::time::sleep;
+pub mod ip;
 
-use crate::models::google::{
-    Instance, InstancesResponse, IpConfiguration, Operation, OperationStatus, Project,
-    ProjectsResponse, Settings,
+use chrono::{DateTime, Utc};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    time::Duration,
 };
-use crate::models::{InstanceUpdateDto, NetworkUpdateDto};
-use crate::{error, GError, GResult};
-
-pub async fn auth_token() -> String {
-    let gcloud_auth_cmd = Command::new("gcloud")
-        .arg("auth")
-        .arg("print-access-token")
-        .output()
-        .unwrap_or_else(|_| {
-            error!("Unable get auth token, please make sure gcloud CLI is installed and you're logged in.");
-            std::process::exit(1);
-        });
-    let access_token = String::from_utf8(gcloud_auth_cmd.stdout).unwrap();
-
-    return access_token.trim().to_string();
+
+use crate::client::HttpClient;
+use crate::config::GConfig;
+use crate::models::cli::IpArgs;
+use crate::models::google::AuthorizedNetwork;
+use crate::{warn, GError, GResult};
+use ip::IpDiscovery;
+
+pub async fn current_ip(
+    http: &HttpClient,
+    config: &GConfig,
+    ip_args: &IpArgs,
+    ip_endpoint: Option<&str>,
+) -> GResult<IpAddr> {
+    let discovery = IpDiscovery::new(config, ip_args.ip, ip_args.is_ipv6(), ip_args.consensus)?
+        .with_endpoint(ip_endpoint);
+
+    return discovery.discover(http).await;
+}
+
+pub fn max_prefix(ipv6: bool) -> u8 {
+    return if ipv6 { 128 } else { 32 };
 }
 
-pub async fn current_ip_cidr_notation() -> String {
-    let client = Client::new();
-    let checkip_response = client
-        .get("https://checkip.amazonaws.com")
-        .send()
-        .await
-        .expect("Failed to get IP address")
-        .text()
-        .await
-        .expect("Failed to parse IP address response");
-    let current_ip = checkip_response.trim();
-    let current_ip_octets: Vec<&str> = current_ip.split(".").collect();
-    let current_ip_cidr_notation = format!(
-        "{}.{}.{}.0/24",
-        current_ip_octets[0], current_ip_octets[1], current_ip_octets[2]
-    );
-
-    return current_ip_cidr_notation;
+pub fn validate_prefix(prefix: u8, ipv6: bool) -> GResult<()> {
+    if prefix > max_prefix(ipv6) {
+        return Err(GError::InvalidInput(format!(
+            "Invalid prefix `{}` for IPv{} addresses, expected 0-{}",
+            prefix,
+            if ipv6 { 6 } else { 4 },
+            max_prefix(ipv6)
+        )));
+    }
+
+    return Ok(());
 }
 
-pub async fn fetch_projects() -> Vec<Project> {
-    let access_token = auth_token().await;
-
-    let client = Client::new();
-    let projects_response = client
-        .get("https://cloudresourcemanager.googleapis.com/v1/projects")
-        .header(header::CONTENT_TYPE, "application/json")
-        .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
-        .query(&[("filter", "lifecycleState:ACTIVE parent.type:organization")])
-        .send()
-        .await
-        .expect("Failed to send request to the API")
-        .text()
-        .await
-        .expect("Failed to read API response body as text");
-    let ProjectsResponse { projects } = serde_json::from_str(&projects_response).expect(&format!(
-        "Failed to parse API response as JSON: {}",
-        projects_response
-    ));
-
-    return projects;
+pub fn cidr_notation(ip: IpAddr, prefix: u8) -> GResult<String> {
+    validate_prefix(prefix, ip.is_ipv6())?;
+
+    let network_address = match ip {
+        IpAddr::V4(ip) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+        }
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+        }
+    };
+
+    return Ok(format!("{}/{}", network_address, prefix));
 }
 
-pub async fn fetch_instances(project_id: &str) -> Vec<Instance> {
-    let access_token = auth_token().await;
-
-    let client = Client::new();
-    let instances_response = client
-        .get(format!(
-            "https://sqladmin.googleapis.com/v1/projects/{}/instances",
-            project_id
-        ))
-        .header(header::CONTENT_TYPE, "application/json")
-        .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
-        .query(&[("filter", "state:RUNNABLE instanceType:CLOUD_SQL_INSTANCE")])
-        .send()
-        .await
-        .expect("Failed to send request to the API")
-        .text()
-        .await
-        .expect("Failed to read API response body as text");
-    let InstancesResponse { items } = serde_json::from_str(&instances_response).expect(&format!(
-        "Failed to parse API response as JSON: {}",
-        instances_response
-    ));
-
-    return items;
+pub fn parse_cidr(value: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match value.split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix)),
+        None => (value, None),
+    };
+    let address: IpAddr = address.parse().ok()?;
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse::<u8>().ok()?,
+        None => max_prefix(address.is_ipv6()),
+    };
+    if prefix > max_prefix(address.is_ipv6()) {
+        return None;
+    }
+
+    return Some((address, prefix));
+}
+
+pub fn validate_network_value(value: &str) -> GResult<()> {
+    if parse_cidr(value).is_none() {
+        return Err(GError::InvalidInput(format!(
+            "Invalid network value `{}`, expected an IP address or CIDR notation",
+            value
+        )));
+    }
+
+    return Ok(());
+}
+
+pub fn validate_expiration_time(expiration_time: &str) -> GResult<()> {
+    if DateTime::parse_from_rfc3339(expiration_time).is_err() {
+        return Err(GError::InvalidInput(format!(
+            "Invalid expiration time `{}`, expected RFC 3339 format, e.g. `2030-01-01T00:00:00Z`",
+            expiration_time
+        )));
+    }
+
+    return Ok(());
+}
+
+/// Network address of a value in CIDR notation, so `10.0.0.5/24` and `10.0.0.0/24` or
+/// `1.2.3.4` and `1.2.3.4/32` compare equal
+pub fn normalize_network_value(value: &str) -> Option<String> {
+    let (address, prefix) = parse_cidr(value)?;
+
+    return cidr_notation(address, prefix).ok();
+}
+
+pub fn network_contains(value: &str, ip: IpAddr) -> bool {
+    let Some((address, prefix)) = parse_cidr(value) else {
+        return false;
+    };
+    if address.is_ipv6() != ip.is_ipv6() {
+        return false;
+    }
+
+    return match (cidr_notation(address, prefix), cidr_notation(ip, prefix)) {
+        (Ok(network), Ok(ip_network)) => network == ip_network,
+        _ => false,
+    };
 }
 
-pub async fn fetch_instance(project_id: &str, instance_id: &str) -> GResult<Instance> {
-    let access_token = auth_token().await;
-
-    let client = Client::new();
-    let instances_response = client
-        .get(format!(
-            "https://sqladmin.googleapis.com/v1/projects/{}/instances/{}",
-            project_id, instance_id
-        ))
-        .header(header::CONTENT_TYPE, "application/json")
-        .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
-        .send()
-        .await
-        .expect("Failed to send request to the API")
-        .text()
-        .await
-        .expect("Failed to read API response body as text");
-    let instance: Instance = serde_json::from_str(&instances_response).expect(&format!(
-        "Failed to parse API response as JSON: {}",
-        instances_response
-    ));
-
-    return Ok(instance);
+pub async fn current_ip_cidr_notation(
+    http: &HttpClient,
+    config: &GConfig,
+    prefix: u8,
+    ip_args: &IpArgs,
+    ip_endpoint: Option<&str>,
+) -> GResult<String> {
+    let current_ip = current_ip(http, config, ip_args, ip_endpoint).await?;
+
+    return cidr_notation(current_ip, prefix);
 }
 
-pub async fn update_instance_network(
-    instance: &Instance,
-    network_update_dto: NetworkUpdateDto,
-) -> Operation {
-    let access_token = auth_token().await;
-    let mut authorized_networks = instance
-        .settings
-        .ip_configuration
-        .authorized_networks
-        .clone();
-    let network_index = authorized_networks
-        .iter()
-        .position(|network| network.name == network_update_dto.name)
-        .unwrap_or_else(|| {
-            error!(
-                "Unable to find the network `{}`, instance[{}] project[{}]",
-                network_update_dto.name, instance.name, instance.project
-            );
-            std::process::exit(1);
-        });
-    authorized_networks[network_index].value = network_update_dto.value;
-
-    let instance_update_dto = InstanceUpdateDto {
-        settings: Settings {
-            ip_configuration: IpConfiguration {
-                authorized_networks,
-            },
+pub fn is_network_expired(network: &AuthorizedNetwork) -> bool {
+    return match &network.expiration_time {
+        Some(expiration_time) => match DateTime::parse_from_rfc3339(expiration_time) {
+            Ok(expiration_time) => expiration_time < Utc::now(),
+            Err(_) => {
+                warn!(
+                    "Unable to parse expiration time `{}` of network `{}`",
+                    expiration_time, network.name
+                );
+                false
+            }
         },
+        None => false,
     };
+}
+
+/// Random value from the standard library's per-process hasher keys, good enough for jitter and
+/// request IDs
+pub fn random_u64() -> u64 {
+    return RandomState::new().build_hasher().finish();
+}
+
+/// Randomizes `duration` between half and its full length so retries don't synchronize
+pub fn jitter(duration: Duration) -> Duration {
+    let half = duration / 2;
+    let nanos = half.as_nanos() as u64;
+    if nanos == 0 {
+        return duration;
+    }
 
-    let client = Client::new();
-    let instance_update_response = client
-        .patch(format!(
-            "https://sqladmin.googleapis.com/v1/projects/{}/instances/{}",
-            instance.project, instance.name
-        ))
-        .header(header::CONTENT_TYPE, "application/json")
-        .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
-        .body(
-            serde_json::to_string(&instance_update_dto)
-                .expect("Failed to serialize the instance update DTO"),
-        )
-        .send()
-        .await
-        .expect("Failed to send request to the API")
-        .text()
-        .await
-        .expect("Failed to read API response body as text");
-    let operation: Operation = serde_json::from_str(&instance_update_response).expect(&format!(
-        "Failed to parse API response as JSON: {}",
-        instance_update_response
-    ));
-
-    return operation;
+    return half + Duration::from_nanos(random_u64() % (nanos + 1));
 }
 
-pub async fn operation_status(project_id: &str, operation_id: &str) -> GResult<()> {
-    let mut attempt = 0;
-    let max_attempts = 15;
-    let access_token = auth_token().await;
-
-    let client = Client::new();
-
-    loop {
-        let operation_response = client
-            .get(format!(
-                "https://sqladmin.googleapis.com/v1/projects/{}/operations/{}",
-                project_id, operation_id,
-            ))
-            .header(header::CONTENT_TYPE, "application/json")
-            .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
-            .send()
-            .await
-            .expect("Failed to send request to the API")
-            .text()
-            .await
-            .expect("Failed to read API response body as text");
-        let operation: Operation = serde_json::from_str(&operation_response).expect(&format!(
-            "Failed to parse API response as JSON: {}",
-            operation_response
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4(value: &str) -> IpAddr {
+        return value.parse().unwrap();
+    }
+
+    #[test]
+    fn cidr_notation_masks_ipv4_host_bits() {
+        let ip = ipv4("203.0.113.7");
+
+        assert_eq!(cidr_notation(ip, 0).unwrap(), "0.0.0.0/0");
+        assert_eq!(cidr_notation(ip, 24).unwrap(), "203.0.113.0/24");
+        assert_eq!(cidr_notation(ip, 32).unwrap(), "203.0.113.7/32");
+    }
+
+    #[test]
+    fn cidr_notation_rejects_prefix_longer_than_address() {
+        assert!(matches!(
+            cidr_notation(ipv4("203.0.113.7"), 33),
+            Err(GError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            cidr_notation(ipv4("203.0.113.7"), 64),
+            Err(GError::InvalidInput(_))
         ));
+    }
 
-        match operation.status {
-            OperationStatus::Done => {
-                return Ok(());
-            }
-            OperationStatus::Unspecified => {
-                return Err(GError::Unknown("Unknown status".into()));
-            }
-            OperationStatus::Pending | OperationStatus::Running => {}
-        }
+    #[test]
+    fn parse_cidr_defaults_to_host_prefix() {
+        assert_eq!(parse_cidr("203.0.113.7"), Some((ipv4("203.0.113.7"), 32)));
+        assert_eq!(
+            parse_cidr("203.0.113.0/24"),
+            Some((ipv4("203.0.113.0"), 24))
+        );
+    }
 
-        attempt += 1;
+    #[test]
+    fn parse_cidr_rejects_invalid_values() {
+        assert_eq!(parse_cidr("203.0.113.0/33"), None);
+        assert_eq!(parse_cidr("203.0.113.0/-1"), None);
+        assert_eq!(parse_cidr("203.0.113.0/"), None);
+        assert_eq!(parse_cidr("203.0.113/24"), None);
+        assert_eq!(parse_cidr("example.com"), None);
+    }
 
-        if attempt > max_attempts {
-            return Err(GError::Timeout("Max retries reached".into()));
-        }
+    #[test]
+    fn normalize_network_value_masks_host_bits() {
+        assert_eq!(
+            normalize_network_value("10.0.0.5/24").as_deref(),
+            Some("10.0.0.0/24")
+        );
+        assert_eq!(
+            normalize_network_value("1.2.3.4").as_deref(),
+            Some("1.2.3.4/32")
+        );
+        assert_eq!(normalize_network_value("10.0.0.5/33"), None);
+    }
+
+    #[test]
+    fn network_contains_matches_ipv4_networks() {
+        assert!(network_contains("203.0.113.0/24", ipv4("203.0.113.7")));
+        assert!(network_contains("203.0.113.7", ipv4("203.0.113.7")));
+        assert!(network_contains("0.0.0.0/0", ipv4("203.0.113.7")));
+        assert!(!network_contains("203.0.113.0/24", ipv4("198.51.100.7")));
+        assert!(!network_contains("203.0.113.0/33", ipv4("203.0.113.7")));
+    }
+
+    #[test]
+    fn cidr_notation_masks_ipv6_host_bits() {
+        let ip: IpAddr = "2001:db8:1:2:3:4:5:6".parse().unwrap();
+
+        assert_eq!(cidr_notation(ip, 64).unwrap(), "2001:db8:1:2::/64");
+        assert_eq!(cidr_notation(ip, 128).unwrap(), "2001:db8:1:2:3:4:5:6/128");
+        assert!(matches!(
+            cidr_notation(ip, 129),
+            Err(GError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn network_contains_matches_ipv6_networks() {
+        let ip: IpAddr = "2001:db8:1:2::7".parse().unwrap();
 
-        let wait_time = Duration::from_secs(2_u64.pow(attempt));
-        sleep(wait_time).await;
+        assert_eq!(parse_cidr("2001:db8:1:2::7"), Some((ip, 128)));
+        assert_eq!(parse_cidr("2001:db8::/129"), None);
+        assert!(network_contains("2001:db8:1:2::/64", ip));
+        assert!(!network_contains("2001:db8:1:3::/64", ip));
+        // Never matches across address families
+        assert!(!network_contains("0.0.0.0/0", ip));
+        assert!(!network_contains("::/0", ipv4("203.0.113.7")));
     }
 }
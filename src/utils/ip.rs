@@ -0,0 +1,480 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+use reqwest::Client;
+use tokio::{net::UdpSocket, time::timeout};
+
+use crate::{client::HttpClient, config::GConfig, warn, GError, GResult};
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 5;
+
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_RESPONSE: u16 = 0x0101;
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+const STUN_MAPPED_ADDRESS: u16 = 0x0001;
+const STUN_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+#[derive(Clone, Debug)]
+pub enum IpProvider {
+    /// Fixed address, used for the `--ip` override
+    Static(IpAddr),
+    /// Plain text response containing only the address
+    Text { url: String },
+    /// JSON object response containing the address under `field`
+    Json { url: String, field: String },
+    /// STUN binding request against `server` in `host:port` form
+    Stun { server: String },
+}
+
+impl IpProvider {
+    /// Parses a configured provider, either an HTTP(S) URL or `stun:host:port`
+    pub fn parse(provider: &str) -> GResult<Self> {
+        if let Some(server) = provider.strip_prefix("stun:") {
+            return Ok(Self::Stun {
+                server: server.to_string(),
+            });
+        }
+        if provider.starts_with("http://") || provider.starts_with("https://") {
+            return Ok(Self::Text {
+                url: provider.to_string(),
+            });
+        }
+
+        return Err(GError::InvalidInput(format!(
+            "Invalid IP provider `{}`, expected an HTTP(S) URL or `stun:host:port`",
+            provider
+        )));
+    }
+    pub fn defaults(ipv6: bool) -> Vec<Self> {
+        if ipv6 {
+            return vec![
+                Self::Json {
+                    url: "https://api6.ipify.org?format=json".into(),
+                    field: "ip".into(),
+                },
+                Self::Text {
+                    url: "https://ipv6.icanhazip.com".into(),
+                },
+                Self::Stun {
+                    server: "stun.l.google.com:19302".into(),
+                },
+            ];
+        }
+
+        return vec![
+            Self::Text {
+                url: "https://checkip.amazonaws.com".into(),
+            },
+            Self::Json {
+                url: "https://api.ipify.org?format=json".into(),
+                field: "ip".into(),
+            },
+            Self::Text {
+                url: "https://ipv4.icanhazip.com".into(),
+            },
+            Self::Stun {
+                server: "stun.l.google.com:19302".into(),
+            },
+        ];
+    }
+    pub fn name(&self) -> String {
+        return match self {
+            Self::Static(ip) => format!("static:{}", ip),
+            Self::Text { url } | Self::Json { url, .. } => url.clone(),
+            Self::Stun { server } => format!("stun:{}", server),
+        };
+    }
+    pub async fn lookup(&self, client: &Client, ipv6: bool) -> GResult<IpAddr> {
+        let ip = match self {
+            Self::Static(ip) => *ip,
+            Self::Text { url } => {
+                let response = http_get(client, url).await?;
+                // Configured URLs may answer with JSON as well, e.g. `?format=json`
+                if response.trim_start().starts_with('{') {
+                    parse_json_ip(&response, "ip")?
+                } else {
+                    parse_ip(&response)?
+                }
+            }
+            Self::Json { url, field } => {
+                let response = http_get(client, url).await?;
+                parse_json_ip(&response, field)?
+            }
+            Self::Stun { server } => stun_lookup(server, ipv6).await?,
+        };
+
+        if ip.is_ipv6() != ipv6 {
+            return Err(GError::Unknown(format!(
+                "Expected an IPv{} address but got `{}`",
+                if ipv6 { 6 } else { 4 },
+                ip
+            )));
+        }
+
+        return Ok(ip);
+    }
+}
+
+pub struct IpDiscovery {
+    pub providers: Vec<IpProvider>,
+    pub timeout: Duration,
+    pub consensus: bool,
+    pub ipv6: bool,
+}
+
+impl IpDiscovery {
+    pub fn new(config: &GConfig, ip: Option<IpAddr>, ipv6: bool, consensus: bool) -> GResult<Self> {
+        let timeout = Duration::from_secs(
+            config
+                .network
+                .ip_timeout_secs
+                .unwrap_or(DEFAULT_TIMEOUT_SECS),
+        );
+        let consensus = consensus || config.network.ip_consensus;
+
+        if let Some(ip) = ip {
+            if ipv6 && !ip.is_ipv6() {
+                return Err(GError::InvalidInput(format!(
+                    "`--ip {}` is an IPv4 address but an IPv6 address was requested, either \
+                     with `-6` or by a target saved with `-6`",
+                    ip
+                )));
+            }
+            return Ok(Self {
+                providers: vec![IpProvider::Static(ip)],
+                timeout,
+                consensus: false,
+                ipv6: ip.is_ipv6(),
+            });
+        }
+
+        let providers = if config.network.ip_providers.is_empty() {
+            IpProvider::defaults(ipv6)
+        } else {
+            config
+                .network
+                .ip_providers
+                .iter()
+                .map(|provider| IpProvider::parse(provider))
+                .collect::<GResult<Vec<_>>>()?
+        };
+
+        return Ok(Self {
+            providers,
+            timeout,
+            consensus,
+            ipv6,
+        });
+    }
+    /// Replaces the providers with `url`, unless a fixed address is used
+    pub fn with_endpoint(mut self, url: Option<&str>) -> Self {
+        if let Some(url) = url
+            && !matches!(self.providers.as_slice(), [IpProvider::Static(_)])
+        {
+            self.providers = vec![IpProvider::Text { url: url.into() }];
+        }
+        return self;
+    }
+    /// Tries each provider in order, returning the first address or, in consensus mode, the
+    /// first address reported by two different providers. Each lookup is cut short after
+    /// `timeout`, independently of the shared client's request timeout.
+    pub async fn discover(&self, http: &HttpClient) -> GResult<IpAddr> {
+        let mut found: Vec<IpAddr> = Vec::new();
+
+        for provider in &self.providers {
+            let lookup = timeout(self.timeout, provider.lookup(http.client(), self.ipv6)).await;
+            let ip = match lookup {
+                Ok(Ok(ip)) => ip,
+                Ok(Err(message)) => {
+                    warn!("IP provider `{}` failed: {}", provider.name(), message);
+                    continue;
+                }
+                Err(_) => {
+                    warn!("IP provider `{}` timed out", provider.name());
+                    continue;
+                }
+            };
+
+            if !self.consensus || found.contains(&ip) {
+                return Ok(ip);
+            }
+            found.push(ip);
+        }
+
+        if self.consensus && !found.is_empty() {
+            return Err(GError::Unknown(format!(
+                "IP providers did not agree on your current IP, got {}",
+                found
+                    .iter()
+                    .map(|ip| ip.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        return Err(GError::Unknown(
+            "Unable to get your current IP from any provider".into(),
+        ));
+    }
+}
+
+async fn http_get(client: &Client, url: &str) -> GResult<String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| GError::Unknown(err.to_string()))?;
+
+    return response
+        .text()
+        .await
+        .map_err(|err| GError::Unknown(err.to_string()));
+}
+
+fn parse_ip(value: &str) -> GResult<IpAddr> {
+    let value = value.trim();
+
+    return value
+        .parse()
+        .map_err(|_| GError::Unknown(format!("Unable to parse IP address `{}`", value)));
+}
+
+fn parse_json_ip(response: &str, field: &str) -> GResult<IpAddr> {
+    let value: serde_json::Value = serde_json::from_str(response)
+        .map_err(|_| GError::Unknown(format!("Unable to parse JSON response `{}`", response)))?;
+    let ip = value
+        .get(field)
+        .and_then(|ip| ip.as_str())
+        .ok_or_else(|| GError::Unknown(format!("Missing `{}` in JSON response", field)))?;
+
+    return parse_ip(ip);
+}
+
+async fn stun_lookup(server: &str, ipv6: bool) -> GResult<IpAddr> {
+    let server_address = tokio::net::lookup_host(server)
+        .await
+        .map_err(|err| GError::Unknown(err.to_string()))?
+        .find(|address| address.is_ipv6() == ipv6)
+        .ok_or_else(|| {
+            GError::Unknown(format!(
+                "Unable to resolve an IPv{} address for `{}`",
+                if ipv6 { 6 } else { 4 },
+                server
+            ))
+        })?;
+    let bind_address: SocketAddr = if ipv6 {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind_address)
+        .await
+        .map_err(|err| GError::Unknown(err.to_string()))?;
+
+    let mut transaction_id = [0u8; 12];
+    let random = super::random_u64().to_be_bytes();
+    transaction_id[..8].copy_from_slice(&random);
+    transaction_id[8..].copy_from_slice(&std::process::id().to_be_bytes());
+
+    let mut request = Vec::with_capacity(20);
+    request.extend_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(&transaction_id);
+    socket
+        .send_to(&request, server_address)
+        .await
+        .map_err(|err| GError::Unknown(err.to_string()))?;
+
+    let mut response = [0u8; 512];
+    let (length, _) = socket
+        .recv_from(&mut response)
+        .await
+        .map_err(|err| GError::Unknown(err.to_string()))?;
+
+    return parse_stun_response(&response[..length], &transaction_id);
+}
+
+fn parse_stun_response(response: &[u8], transaction_id: &[u8; 12]) -> GResult<IpAddr> {
+    let invalid = || GError::Unknown("Invalid STUN response".into());
+
+    if response.len() < 20
+        || u16::from_be_bytes([response[0], response[1]]) != STUN_BINDING_RESPONSE
+        || response[8..20] != transaction_id[..]
+    {
+        return Err(invalid());
+    }
+
+    let mut offset = 20;
+    let mut mapped_address = None;
+    while offset + 4 <= response.len() {
+        let attribute_type = u16::from_be_bytes([response[offset], response[offset + 1]]);
+        let attribute_length =
+            u16::from_be_bytes([response[offset + 2], response[offset + 3]]) as usize;
+        let value = response
+            .get(offset + 4..offset + 4 + attribute_length)
+            .ok_or_else(invalid)?;
+
+        match attribute_type {
+            STUN_XOR_MAPPED_ADDRESS => {
+                return parse_stun_address(value, Some(transaction_id)).ok_or_else(invalid);
+            }
+            STUN_MAPPED_ADDRESS => {
+                mapped_address = parse_stun_address(value, None);
+            }
+            _ => {}
+        }
+
+        // Attributes are padded to a multiple of 4 bytes
+        offset += 4 + attribute_length.div_ceil(4) * 4;
+    }
+
+    return mapped_address.ok_or_else(invalid);
+}
+
+fn parse_stun_address(value: &[u8], xor_transaction_id: Option<&[u8; 12]>) -> Option<IpAddr> {
+    let family = *value.get(1)?;
+    let mut key = STUN_MAGIC_COOKIE.to_be_bytes().to_vec();
+    if let Some(transaction_id) = xor_transaction_id {
+        key.extend_from_slice(transaction_id);
+    }
+    let xor = |bytes: &[u8]| -> Vec<u8> {
+        match xor_transaction_id {
+            Some(_) => bytes
+                .iter()
+                .zip(&key)
+                .map(|(byte, key)| byte ^ key)
+                .collect(),
+            None => bytes.to_vec(),
+        }
+    };
+
+    return match family {
+        0x01 => {
+            let octets: [u8; 4] = xor(value.get(4..8)?).try_into().ok()?;
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        0x02 => {
+            let octets: [u8; 16] = xor(value.get(4..20)?).try_into().ok()?;
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSACTION_ID: [u8; 12] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+
+    fn stun_response(attributes: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (attribute_type, value) in attributes {
+            body.extend_from_slice(&attribute_type.to_be_bytes());
+            body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            body.extend_from_slice(value);
+            body.resize(body.len().div_ceil(4) * 4, 0);
+        }
+
+        let mut response = Vec::new();
+        response.extend_from_slice(&STUN_BINDING_RESPONSE.to_be_bytes());
+        response.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        response.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        response.extend_from_slice(&TRANSACTION_ID);
+        response.extend_from_slice(&body);
+        return response;
+    }
+
+    fn address_value(family: u8, address: &[u8], key: &[u8]) -> Vec<u8> {
+        let mut value = vec![0, family, 0, 0];
+        value.extend(address.iter().zip(key).map(|(byte, key)| byte ^ key));
+        return value;
+    }
+
+    fn xor_key() -> Vec<u8> {
+        let mut key = STUN_MAGIC_COOKIE.to_be_bytes().to_vec();
+        key.extend_from_slice(&TRANSACTION_ID);
+        return key;
+    }
+
+    #[test]
+    fn parses_ipv4_xor_mapped_address() {
+        let response = stun_response(&[(
+            STUN_XOR_MAPPED_ADDRESS,
+            address_value(0x01, &[203, 0, 113, 7], &xor_key()),
+        )]);
+
+        assert_eq!(
+            parse_stun_response(&response, &TRANSACTION_ID).unwrap(),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_ipv6_xor_mapped_address() {
+        let ip: Ipv6Addr = "2001:db8:1:2::7".parse().unwrap();
+        let response = stun_response(&[(
+            STUN_XOR_MAPPED_ADDRESS,
+            address_value(0x02, &ip.octets(), &xor_key()),
+        )]);
+
+        assert_eq!(
+            parse_stun_response(&response, &TRANSACTION_ID).unwrap(),
+            IpAddr::V6(ip)
+        );
+    }
+
+    #[test]
+    fn parses_mapped_address_without_xor_mapped_address() {
+        let response = stun_response(&[
+            (0x8022, b"server".to_vec()),
+            (
+                STUN_MAPPED_ADDRESS,
+                address_value(0x01, &[198, 51, 100, 7], &[0; 4]),
+            ),
+        ]);
+
+        assert_eq!(
+            parse_stun_response(&response, &TRANSACTION_ID).unwrap(),
+            "198.51.100.7".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn prefers_xor_mapped_address() {
+        let response = stun_response(&[
+            (
+                STUN_MAPPED_ADDRESS,
+                address_value(0x01, &[10, 0, 0, 7], &[0; 4]),
+            ),
+            (
+                STUN_XOR_MAPPED_ADDRESS,
+                address_value(0x01, &[203, 0, 113, 7], &xor_key()),
+            ),
+        ]);
+
+        assert_eq!(
+            parse_stun_response(&response, &TRANSACTION_ID).unwrap(),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_responses() {
+        let response = stun_response(&[(
+            STUN_XOR_MAPPED_ADDRESS,
+            address_value(0x01, &[203, 0, 113, 7], &xor_key()),
+        )]);
+
+        // Answer to another request
+        assert!(parse_stun_response(&response, &[0; 12]).is_err());
+        // Truncated attribute
+        assert!(parse_stun_response(&response[..response.len() - 2], &TRANSACTION_ID).is_err());
+        // No address attribute
+        assert!(parse_stun_response(&stun_response(&[]), &TRANSACTION_ID).is_err());
+    }
+}
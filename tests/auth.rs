@@ -0,0 +1,482 @@
+mod common;
+
+use std::{collections::HashMap, process::Output};
+
+use chrono::{Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{body_json, header, method, path},
+    Mock, Request, ResponseTemplate,
+};
+
+use common::{stderr_and_stdout, TestEnv, ACCESS_TOKEN, INSTANCE, PROJECT};
+
+const SERVICE_ACCOUNT: &str = "deployer@my-project.iam.gserviceaccount.com";
+const IMPERSONATED_ACCESS_TOKEN: &str = "impersonated-access-token";
+
+#[tokio::test]
+async fn metadata_server_token_is_used_without_credentials_file() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/computeMetadata/v1/instance/service-accounts/default/token",
+        ))
+        .and(header("metadata-flavor", "Google"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": ACCESS_TOKEN,
+            "expires_in": 3600,
+            "token_type": "Bearer",
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let metadata_host = env.server.uri().replace("http://", "");
+    let output = env
+        .command(&[
+            "network",
+            "list",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "--ip",
+            "192.0.2.7",
+            "-o",
+            "csv",
+        ])
+        .env_remove("GOOGLE_APPLICATION_CREDENTIALS")
+        .env("GCE_METADATA_HOST", metadata_host)
+        // Keeps a locally installed gcloud from answering first
+        .env("PATH", env.home.path())
+        .output()
+        .await
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("home,192.0.2.0/24"));
+}
+
+fn token_requests(requests: &[Request]) -> Vec<HashMap<String, String>> {
+    return requests
+        .iter()
+        .filter(|request| request.method.as_str() == "POST" && request.url.path() == "/token")
+        .map(|request| form(&String::from_utf8_lossy(&request.body)))
+        .collect();
+}
+
+/// Decodes an `application/x-www-form-urlencoded` body
+fn form(body: &str) -> HashMap<String, String> {
+    let decode = |value: &str| {
+        let value = value.replace('+', " ");
+        let bytes = value.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut index = 0;
+        while index < bytes.len() {
+            if bytes[index] == b'%' && index + 2 < bytes.len() {
+                let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap();
+                decoded.push(u8::from_str_radix(hex, 16).unwrap());
+                index += 3;
+            } else {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+        String::from_utf8(decoded).unwrap()
+    };
+
+    return body
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (decode(key), decode(value)))
+        .collect();
+}
+
+async fn list_networks(env: &TestEnv) -> Output {
+    return env
+        .run(&[
+            "network",
+            "list",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "--ip",
+            "192.0.2.7",
+            "-o",
+            "csv",
+        ])
+        .await;
+}
+
+#[tokio::test]
+async fn service_account_key_is_exchanged_for_token() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.write_credentials(json!({
+        "type": "service_account",
+        "client_email": SERVICE_ACCOUNT,
+        "private_key": include_str!("fixtures/service_account_key.pem"),
+        "private_key_id": "key-id",
+        "token_uri": env.token_uri(),
+    }));
+
+    let output = list_networks(&env).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let requests = token_requests(&env.server.received_requests().await.unwrap());
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0]["grant_type"],
+        "urn:ietf:params:oauth:grant-type:jwt-bearer"
+    );
+
+    let assertion = &requests[0]["assertion"];
+    let header = jsonwebtoken::decode_header(assertion).unwrap();
+    assert_eq!(header.alg, Algorithm::RS256);
+    assert_eq!(header.kid.as_deref(), Some("key-id"));
+    let decoding_key =
+        DecodingKey::from_rsa_pem(include_bytes!("fixtures/service_account_key.pub.pem")).unwrap();
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_audience(&[env.token_uri()]);
+    let claims = jsonwebtoken::decode::<Value>(assertion, &decoding_key, &validation)
+        .unwrap()
+        .claims;
+    assert_eq!(claims["iss"], SERVICE_ACCOUNT);
+    assert_eq!(
+        claims["scope"],
+        "https://www.googleapis.com/auth/cloud-platform"
+    );
+}
+
+#[tokio::test]
+async fn authorized_user_refresh_token_is_exchanged_for_token() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+
+    let output = list_networks(&env).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let requests = token_requests(&env.server.received_requests().await.unwrap());
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0],
+        HashMap::from([
+            ("grant_type".into(), "refresh_token".into()),
+            ("client_id".into(), "client-id".into()),
+            ("client_secret".into(), "client-secret".into()),
+            ("refresh_token".into(), "refresh-token".into()),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn token_exchange_error_fails_with_auth_exit_code() {
+    let env = TestEnv::new().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": "invalid_grant",
+            "error_description": "Token has been expired or revoked.",
+        })))
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+
+    let output = list_networks(&env).await;
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr_and_stdout(&output)
+        .contains("invalid_grant: Token has been expired or revoked."));
+}
+
+async fn update_network(env: &TestEnv) -> Output {
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.0/24", 1).await;
+
+    return env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+}
+
+#[tokio::test]
+async fn token_is_reused_across_requests() {
+    let env = TestEnv::new().await;
+
+    let output = update_network(&env).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    // Fetching the instance, patching it and polling the operation share one token
+    let requests = token_requests(&env.server.received_requests().await.unwrap());
+    assert_eq!(requests.len(), 1);
+}
+
+#[tokio::test]
+async fn token_is_refreshed_near_expiry() {
+    let env = TestEnv::new().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "access_token": ACCESS_TOKEN,
+            "expires_in": 30,
+        })))
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+
+    let output = update_network(&env).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    let requests = token_requests(&env.server.received_requests().await.unwrap());
+    assert_eq!(requests.len(), 3);
+}
+
+#[tokio::test]
+async fn token_is_invalidated_after_unauthorized_response() {
+    let env = TestEnv::new().await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": {
+                "code": 401,
+                "message": "Request had invalid authentication credentials.",
+                "status": "UNAUTHENTICATED",
+            },
+        })))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let output = update_network(&env).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    let requests = token_requests(&env.server.received_requests().await.unwrap());
+    assert_eq!(requests.len(), 2);
+}
+
+#[tokio::test]
+async fn rejected_credentials_hint_covers_every_credentials_source() {
+    let env = TestEnv::new().await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": {
+                "code": 401,
+                "message": "Request had invalid authentication credentials.",
+                "status": "UNAUTHENTICATED",
+            },
+        })))
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+
+    let output = list_networks(&env).await;
+    assert_eq!(output.status.code(), Some(6));
+    let output = stderr_and_stdout(&output);
+    assert!(output.contains("`gcloud auth login` for gcloud"));
+    assert!(output.contains("`gcloud auth application-default login`"));
+    assert!(output.contains("service account key file"));
+}
+
+#[tokio::test]
+async fn persisted_token_is_private_and_reused_across_invocations() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.write_config(json!({ "auth": { "persist_token_cache": true } }));
+
+    for _ in 0..2 {
+        let output = list_networks(&env).await;
+        assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    }
+    let requests = token_requests(&env.server.received_requests().await.unwrap());
+    assert_eq!(requests.len(), 1);
+
+    let token_path = env.home.path().join("data/gsqueal/token.json");
+    let cached_token: Value =
+        serde_json::from_str(&std::fs::read_to_string(&token_path).unwrap()).unwrap();
+    assert_eq!(cached_token["access_token"]["token"], ACCESS_TOKEN);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(&token_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+fn generate_access_token_path() -> String {
+    return format!(
+        "/iam/v1/projects/-/serviceAccounts/{}:generateAccessToken",
+        SERVICE_ACCOUNT
+    );
+}
+
+async fn list_networks_as_service_account(env: &TestEnv) -> Output {
+    return env
+        .command(&[
+            "network",
+            "list",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "--ip",
+            "192.0.2.7",
+            "--impersonate-service-account",
+            SERVICE_ACCOUNT,
+        ])
+        .env(
+            "GSQUEAL_IAMCREDENTIALS_ENDPOINT",
+            format!("{}/iam/v1", env.server.uri()),
+        )
+        .output()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn impersonated_token_is_used_for_api_requests() {
+    let env = TestEnv::new().await;
+    Mock::given(method("POST"))
+        .and(path(generate_access_token_path()))
+        .and(header(
+            "authorization",
+            format!("Bearer {}", ACCESS_TOKEN).as_str(),
+        ))
+        .and(body_json(json!({
+            "scope": ["https://www.googleapis.com/auth/cloud-platform"],
+            "lifetime": "3600s",
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "accessToken": IMPERSONATED_ACCESS_TOKEN,
+            "expireTime": (Utc::now() + Duration::hours(1)).to_rfc3339(),
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .and(header(
+            "authorization",
+            format!("Bearer {}", IMPERSONATED_ACCESS_TOKEN).as_str(),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "project": PROJECT,
+            "name": INSTANCE,
+            "settings": {
+                "ipConfiguration": {
+                    "authorizedNetworks": [
+                        { "name": "home", "value": "192.0.2.0/24", "kind": "sql#aclEntry" },
+                    ],
+                },
+            },
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let output = list_networks_as_service_account(&env).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}
+
+#[tokio::test]
+async fn impersonation_failure_explains_required_role() {
+    let env = TestEnv::new().await;
+    Mock::given(method("POST"))
+        .and(path(generate_access_token_path()))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "error": {
+                "code": 403,
+                "message": "Permission 'iam.serviceAccounts.getAccessToken' denied",
+                "status": "PERMISSION_DENIED",
+            },
+        })))
+        .mount(&env.server)
+        .await;
+
+    let output = list_networks_as_service_account(&env).await;
+    assert_eq!(output.status.code(), Some(3));
+    let output = stderr_and_stdout(&output);
+    assert!(output.contains("Permission 'iam.serviceAccounts.getAccessToken' denied"));
+    assert!(output.contains("Service Account Token Creator"));
+}
+
+#[tokio::test]
+async fn token_and_ip_requests_use_configured_http_client() {
+    let env = TestEnv::new().await;
+    env.write_config(json!({ "http": { "user_agent": "gsqueal-test" } }));
+    env.mock_instance("203.0.113.0/24").await;
+    env.mock_current_ip("203.0.113.7").await;
+
+    let output = env
+        .run(&[
+            "network", "list", "-p", PROJECT, "-i", INSTANCE, "-o", "csv",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let requests = env.server.received_requests().await.unwrap();
+    for request_path in ["/token", "/ip"] {
+        let request = requests
+            .iter()
+            .find(|request| request.url.path() == request_path)
+            .unwrap_or_else(|| panic!("no request to {}", request_path));
+        assert_eq!(request.headers["user-agent"], "gsqueal-test");
+    }
+}
+
+#[tokio::test]
+async fn rate_limited_token_exchange_is_retried() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+
+    let output = list_networks(&env).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let requests = token_requests(&env.server.received_requests().await.unwrap());
+    assert_eq!(requests.len(), 2);
+}
+
+#[tokio::test]
+async fn rate_limited_impersonation_is_retried() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("POST"))
+        .and(path(generate_access_token_path()))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&env.server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(generate_access_token_path()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "accessToken": ACCESS_TOKEN,
+            "expireTime": (Utc::now() + Duration::hours(1)).to_rfc3339(),
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let output = list_networks_as_service_account(&env).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}
@@ -0,0 +1,248 @@
+#![allow(dead_code)]
+
+use std::{path::PathBuf, process::Output};
+
+use gsqueal::{
+    auth::{Credentials, CredentialsProvider},
+    client::{ApiClient, ClientOptions},
+};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tokio::process::Command;
+use wiremock::{
+    matchers::{body_partial_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+pub const PROJECT: &str = "my-project";
+pub const INSTANCE: &str = "my-instance";
+pub const ETAG: &str = "etag-1";
+pub const SETTINGS_VERSION: &str = "7";
+pub const ACCESS_TOKEN: &str = "test-access-token";
+
+/// Isolated home directory with credentials and endpoints pointing at a mock server
+pub struct TestEnv {
+    pub server: MockServer,
+    pub home: TempDir,
+}
+
+impl TestEnv {
+    pub async fn new() -> Self {
+        let server = MockServer::start().await;
+        let home = TempDir::new().unwrap();
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": ACCESS_TOKEN,
+                "expires_in": 3600,
+            })))
+            .mount(&server)
+            .await;
+
+        let env = Self { server, home };
+        env.write_credentials(json!({
+            "type": "authorized_user",
+            "client_id": "client-id",
+            "client_secret": "client-secret",
+            "refresh_token": "refresh-token",
+            "token_uri": env.token_uri(),
+        }));
+
+        return env;
+    }
+
+    pub fn token_uri(&self) -> String {
+        return format!("{}/token", self.server.uri());
+    }
+
+    /// Replaces the credentials file `GOOGLE_APPLICATION_CREDENTIALS` points at
+    pub fn write_credentials(&self, credentials: Value) {
+        std::fs::write(
+            self.home.path().join("credentials.json"),
+            credentials.to_string(),
+        )
+        .unwrap();
+    }
+
+    pub fn write_config(&self, config: Value) {
+        let config_dir = self.home.path().join("config/gsqueal");
+        std::fs::create_dir_all(&config_dir).unwrap();
+        std::fs::write(config_dir.join("config.json"), config.to_string()).unwrap();
+    }
+
+    pub async fn run(&self, args: &[&str]) -> Output {
+        return self.command(args).output().await.unwrap();
+    }
+
+    /// Command running gsqueal in this environment, for tests that need to adjust it further
+    pub fn command(&self, args: &[&str]) -> Command {
+        let home = self.home.path();
+        let mut command = Command::new(env!("CARGO_BIN_EXE_gsqueal"));
+        command
+            .args(args)
+            .env("HOME", home)
+            .env("XDG_CONFIG_HOME", home.join("config"))
+            .env("XDG_DATA_HOME", home.join("data"))
+            .env(
+                "GOOGLE_APPLICATION_CREDENTIALS",
+                home.join("credentials.json"),
+            )
+            .env(
+                "GSQUEAL_SQLADMIN_ENDPOINT",
+                format!("{}/sql/v1", self.server.uri()),
+            )
+            .env(
+                "GSQUEAL_CLOUDRESOURCEMANAGER_ENDPOINT",
+                format!("{}/crm/v1", self.server.uri()),
+            )
+            .env("GSQUEAL_IP_ENDPOINT", format!("{}/ip", self.server.uri()));
+
+        return command;
+    }
+
+    /// API client authenticating with this environment's credentials file, for tests calling the
+    /// service clients directly instead of running gsqueal
+    pub fn api_client(&self) -> ApiClient {
+        let credentials = Credentials::new(vec![CredentialsProvider::File(
+            self.home.path().join("credentials.json"),
+        )]);
+
+        return ApiClient::new(ClientOptions::default(), credentials).unwrap();
+    }
+
+    pub fn store_path(&self) -> PathBuf {
+        return self.home.path().join("data/gsqueal/store.json");
+    }
+
+    /// Saves the `home` network of the mocked instance as target `alias`, see `mock_instance`
+    pub async fn save_target(&self, alias: &str, extra_args: &[&str]) {
+        let mut args = vec![
+            "network", "save", alias, "-p", PROJECT, "-i", INSTANCE, "-n", "home",
+        ];
+        args.extend_from_slice(extra_args);
+
+        let output = self.run(&args).await;
+        assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    }
+
+    pub async fn mock_current_ip(&self, ip: &str) {
+        Mock::given(method("GET"))
+            .and(path("/ip"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("{}\n", ip)))
+            .mount(&self.server)
+            .await;
+    }
+
+    pub async fn mock_instance(&self, network_value: &str) {
+        self.mock_instance_with_networks(json!([
+            { "name": "office", "value": "198.51.100.0/24", "kind": "sql#aclEntry" },
+            { "name": "home", "value": network_value, "kind": "sql#aclEntry" },
+        ]))
+        .await;
+    }
+
+    pub async fn mock_instance_with_networks(&self, authorized_networks: Value) {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/sql/v1/projects/{}/instances/{}",
+                PROJECT, INSTANCE
+            )))
+            .and(header(
+                "authorization",
+                format!("Bearer {}", ACCESS_TOKEN).as_str(),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "project": PROJECT,
+                "name": INSTANCE,
+                "etag": ETAG,
+                "settings": {
+                    "settingsVersion": SETTINGS_VERSION,
+                    "ipConfiguration": {
+                        "authorizedNetworks": authorized_networks,
+                    },
+                },
+            })))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Accepts any update of the instance, see `update_bodies` for what was sent
+    pub async fn mock_any_update(&self) {
+        Mock::given(method("PATCH"))
+            .and(path(format!(
+                "/sql/v1/projects/{}/instances/{}",
+                PROJECT, INSTANCE
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "operation-1",
+                "status": "PENDING",
+            })))
+            .mount(&self.server)
+            .await;
+        self.mock_operation_done().await;
+    }
+
+    /// Bodies of the PATCH requests received so far
+    pub async fn update_bodies(&self) -> Vec<Value> {
+        return self
+            .server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.method.as_str() == "PATCH")
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect();
+    }
+
+    pub async fn mock_update(&self, expected_value: &str, expected_calls: u64) {
+        Mock::given(method("PATCH"))
+            .and(path(format!(
+                "/sql/v1/projects/{}/instances/{}",
+                PROJECT, INSTANCE
+            )))
+            .and(body_partial_json(json!({
+                "etag": ETAG,
+                "settings": {
+                    "settingsVersion": SETTINGS_VERSION,
+                    "ipConfiguration": {
+                        "authorizedNetworks": [
+                            { "name": "office", "value": "198.51.100.0/24" },
+                            { "name": "home", "value": expected_value },
+                        ],
+                    },
+                },
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "operation-1",
+                "status": "PENDING",
+            })))
+            .expect(expected_calls)
+            .mount(&self.server)
+            .await;
+        self.mock_operation_done().await;
+    }
+
+    async fn mock_operation_done(&self) {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/sql/v1/projects/{}/operations/operation-1",
+                PROJECT
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "operation-1",
+                "status": "DONE",
+            })))
+            .mount(&self.server)
+            .await;
+    }
+}
+
+pub fn stderr_and_stdout(output: &Output) -> String {
+    return format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
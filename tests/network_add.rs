@@ -0,0 +1,156 @@
+mod common;
+
+use serde_json::json;
+
+use common::{stderr_and_stdout, TestEnv, ETAG, INSTANCE, PROJECT};
+
+#[tokio::test]
+async fn add_appends_network_with_expiration_time() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_any_update().await;
+
+    let output = env
+        .run(&[
+            "network",
+            "add",
+            "laptop",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-v",
+            "203.0.113.0/24",
+            "-e",
+            "2030-01-01T00:00:00Z",
+            "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let bodies = env.update_bodies().await;
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies[0]["etag"], ETAG);
+    let networks = bodies[0]["settings"]["ipConfiguration"]["authorizedNetworks"]
+        .as_array()
+        .unwrap();
+    assert_eq!(networks.len(), 3);
+    assert_eq!(networks[0]["name"], "office");
+    assert_eq!(networks[1]["name"], "home");
+    assert_eq!(
+        networks[2],
+        json!({
+            "name": "laptop",
+            "value": "203.0.113.0/24",
+            "kind": "sql#aclEntry",
+            "expirationTime": "2030-01-01T00:00:00Z",
+        })
+    );
+}
+
+#[tokio::test]
+async fn add_defaults_to_current_ip() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_any_update().await;
+
+    let output = env
+        .run(&[
+            "network", "add", "laptop", "-p", PROJECT, "-i", INSTANCE, "--prefix", "32", "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let bodies = env.update_bodies().await;
+    assert_eq!(
+        bodies[0]["settings"]["ipConfiguration"]["authorizedNetworks"][2]["value"],
+        "203.0.113.7/32"
+    );
+}
+
+#[tokio::test]
+async fn add_rejects_existing_name() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_any_update().await;
+
+    let output = env
+        .run(&[
+            "network",
+            "add",
+            "home",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-v",
+            "203.0.113.0/24",
+            "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+    assert!(stderr_and_stdout(&output).contains("Network `home` already exists"));
+    assert!(env.update_bodies().await.is_empty());
+}
+
+#[tokio::test]
+async fn add_rejects_invalid_value() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_any_update().await;
+
+    let output = env
+        .run(&[
+            "network", "add", "laptop", "-p", PROJECT, "-i", INSTANCE, "-v", "203.0.113.0/33",
+            "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+    assert!(env.update_bodies().await.is_empty());
+}
+
+#[tokio::test]
+async fn add_rejects_value_already_authorized_in_another_notation() {
+    let env = TestEnv::new().await;
+    env.mock_instance("203.0.113.7/32").await;
+    env.mock_any_update().await;
+
+    for value in ["203.0.113.7", "198.51.100.42/24"] {
+        let output = env
+            .run(&[
+                "network", "add", "laptop", "-p", PROJECT, "-i", INSTANCE, "-v", value, "-y",
+            ])
+            .await;
+        assert_eq!(output.status.code(), Some(11));
+        assert!(stderr_and_stdout(&output).contains("is already authorized"));
+    }
+    assert!(env.update_bodies().await.is_empty());
+}
+
+#[tokio::test]
+async fn add_rejects_invalid_expiration_time() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_any_update().await;
+
+    let output = env
+        .run(&[
+            "network",
+            "add",
+            "laptop",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-v",
+            "203.0.113.0/24",
+            "-e",
+            "next tuesday",
+            "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+    assert!(stderr_and_stdout(&output).contains("RFC 3339"));
+    assert!(env.update_bodies().await.is_empty());
+}
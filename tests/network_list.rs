@@ -0,0 +1,173 @@
+mod common;
+
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+use common::{stderr_and_stdout, TestEnv, INSTANCE, PROJECT};
+
+async fn list_networks(env: &TestEnv, output_format: &str) -> Output {
+    env.mock_instance_with_networks(json!([
+        { "name": "office", "value": "198.51.100.0/24", "kind": "sql#aclEntry" },
+        {
+            "name": "Home",
+            "value": "203.0.113.0/24",
+            "kind": "sql#aclEntry",
+            "expirationTime": "2030-01-01T00:00:00Z",
+        },
+    ]))
+    .await;
+
+    let output = env
+        .run(&[
+            "network",
+            "list",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "--ip",
+            "203.0.113.7",
+            "-o",
+            output_format,
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    return output;
+}
+
+#[tokio::test]
+async fn list_prints_table() {
+    let env = TestEnv::new().await;
+
+    let output = list_networks(&env, "table").await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("PROJECT     INSTANCE     NAME    VALUE"));
+    assert!(lines[0].ends_with("EXPIRATION TIME       CURRENT IP"));
+    assert_eq!(
+        lines[1].split_whitespace().collect::<Vec<_>>(),
+        [
+            PROJECT,
+            INSTANCE,
+            "Home",
+            "203.0.113.0/24",
+            "sql#aclEntry",
+            "2030-01-01T00:00:00Z",
+            "true",
+        ]
+    );
+    assert_eq!(
+        lines[2].split_whitespace().collect::<Vec<_>>(),
+        [
+            PROJECT,
+            INSTANCE,
+            "office",
+            "198.51.100.0/24",
+            "sql#aclEntry",
+            "false",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn list_prints_json() {
+    let env = TestEnv::new().await;
+
+    let output = list_networks(&env, "json").await;
+    let items: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        items,
+        json!([
+            {
+                "project": PROJECT,
+                "instance": INSTANCE,
+                "name": "Home",
+                "value": "203.0.113.0/24",
+                "kind": "sql#aclEntry",
+                "expirationTime": "2030-01-01T00:00:00Z",
+                "currentIp": true,
+            },
+            {
+                "project": PROJECT,
+                "instance": INSTANCE,
+                "name": "office",
+                "value": "198.51.100.0/24",
+                "kind": "sql#aclEntry",
+                "expirationTime": null,
+                "currentIp": false,
+            },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn list_prints_csv() {
+    let env = TestEnv::new().await;
+
+    let output = list_networks(&env, "csv").await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "project,instance,name,value,kind,expiration_time,current_ip",
+            "my-project,my-instance,Home,203.0.113.0/24,sql#aclEntry,2030-01-01T00:00:00Z,true",
+            "my-project,my-instance,office,198.51.100.0/24,sql#aclEntry,,false",
+        ]
+    );
+}
+
+async fn list_networks_without_current_ip(env: &TestEnv, output_format: &str) -> Output {
+    env.mock_instance("203.0.113.0/24").await;
+
+    // The IP endpoint isn't mocked, so discovering the current IP fails
+    let output = env
+        .run(&[
+            "network",
+            "list",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-o",
+            output_format,
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("CURRENT IP is shown as unknown"));
+
+    return output;
+}
+
+#[tokio::test]
+async fn list_without_current_ip_prints_unknown_csv() {
+    let env = TestEnv::new().await;
+
+    let output = list_networks_without_current_ip(&env, "csv").await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "project,instance,name,value,kind,expiration_time,current_ip",
+            "my-project,my-instance,home,203.0.113.0/24,sql#aclEntry,,unknown",
+            "my-project,my-instance,office,198.51.100.0/24,sql#aclEntry,,unknown",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn list_without_current_ip_prints_null_json() {
+    let env = TestEnv::new().await;
+
+    let output = list_networks_without_current_ip(&env, "json").await;
+    let items: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let current_ips: Vec<_> = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["currentIp"].clone())
+        .collect();
+    assert_eq!(current_ips, [Value::Null, Value::Null]);
+}
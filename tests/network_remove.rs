@@ -0,0 +1,101 @@
+mod common;
+
+use serde_json::{json, Value};
+
+use common::{stderr_and_stdout, TestEnv, INSTANCE, PROJECT};
+
+fn network_names(body: &Value) -> Vec<&str> {
+    return body["settings"]["ipConfiguration"]["authorizedNetworks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|network| network["name"].as_str().unwrap())
+        .collect();
+}
+
+async fn mock_instance(env: &TestEnv) {
+    env.mock_instance_with_networks(json!([
+        { "name": "office", "value": "198.51.100.0/24", "kind": "sql#aclEntry" },
+        { "name": "home", "value": "192.0.2.0/24", "kind": "sql#aclEntry" },
+        {
+            "name": "home-old",
+            "value": "192.0.2.128/25",
+            "kind": "sql#aclEntry",
+            "expirationTime": "2020-01-01T00:00:00Z",
+        },
+        {
+            "name": "contractor",
+            "value": "203.0.113.0/24",
+            "kind": "sql#aclEntry",
+            "expirationTime": "2999-01-01T00:00:00Z",
+        },
+    ]))
+    .await;
+    env.mock_any_update().await;
+}
+
+#[tokio::test]
+async fn remove_deletes_networks_matching_patterns() {
+    let env = TestEnv::new().await;
+    mock_instance(&env).await;
+
+    let output = env
+        .run(&[
+            "network", "remove", "home*", "office", "-p", PROJECT, "-i", INSTANCE, "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let bodies = env.update_bodies().await;
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(network_names(&bodies[0]), ["contractor"]);
+}
+
+#[tokio::test]
+async fn remove_deletes_expired_networks() {
+    let env = TestEnv::new().await;
+    mock_instance(&env).await;
+
+    let output = env
+        .run(&[
+            "network", "remove", "--expired", "-p", PROJECT, "-i", INSTANCE, "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let bodies = env.update_bodies().await;
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(
+        network_names(&bodies[0]),
+        ["office", "home", "contractor"]
+    );
+}
+
+#[tokio::test]
+async fn remove_skips_update_without_matches() {
+    let env = TestEnv::new().await;
+    mock_instance(&env).await;
+
+    let output = env
+        .run(&[
+            "network", "remove", "lab-*", "-p", PROJECT, "-i", INSTANCE, "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(stderr_and_stdout(&output).contains("no networks matched"));
+    assert!(env.update_bodies().await.is_empty());
+}
+
+#[tokio::test]
+async fn remove_rejects_invalid_pattern() {
+    let env = TestEnv::new().await;
+    mock_instance(&env).await;
+
+    let output = env
+        .run(&[
+            "network", "remove", "home[", "-p", PROJECT, "-i", INSTANCE, "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+    assert!(env.update_bodies().await.is_empty());
+}
@@ -0,0 +1,225 @@
+mod common;
+
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use common::{stderr_and_stdout, TestEnv, INSTANCE, PROJECT};
+
+const OTHER_INSTANCE: &str = "other-instance";
+
+#[tokio::test]
+async fn save_stores_named_target() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.save_target("dev", &["--prefix", "32"]).await;
+
+    let store: Value =
+        serde_json::from_str(&std::fs::read_to_string(env.store_path()).unwrap()).unwrap();
+    assert_eq!(
+        store["network"]["targets"]["dev"],
+        json!({
+            "project_id": PROJECT,
+            "instance_id": INSTANCE,
+            "network_name": "home",
+            "prefix": 32,
+            "ipv6": false,
+        })
+    );
+}
+
+#[tokio::test]
+async fn save_rejects_existing_target_without_force() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.save_target("dev", &["--prefix", "32"]).await;
+
+    let output = env
+        .run(&[
+            "network", "save", "dev", "-p", PROJECT, "-i", INSTANCE, "-n", "home",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+
+    let output = env
+        .run(&[
+            "network", "save", "dev", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "--force",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}
+
+#[tokio::test]
+async fn update_uses_saved_target() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.7/32", 1).await;
+    env.save_target("dev", &["--prefix", "32"]).await;
+
+    let output = env.run(&["network", "update", "--target", "dev"]).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}
+
+#[tokio::test]
+async fn update_rejects_ipv4_override_for_ipv6_target() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.7/32", 0).await;
+    env.save_target("dev", &["-6"]).await;
+
+    let output = env
+        .run(&["network", "update", "--target", "dev", "--ip", "203.0.113.7"])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+
+    let output = env
+        .run(&["network", "update", "--all-saved", "--ip", "203.0.113.7"])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[tokio::test]
+async fn update_fails_for_unknown_target() {
+    let env = TestEnv::new().await;
+
+    let output = env.run(&["network", "update", "--target", "missing"]).await;
+    assert_eq!(output.status.code(), Some(10));
+}
+
+#[tokio::test]
+async fn targets_list_and_rm() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.save_target("staging", &["--prefix", "32"]).await;
+    env.save_target("dev", &["--prefix", "32"]).await;
+
+    let output = env.run(&["network", "targets", "list", "-o", "csv"]).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "alias,project,instance,network,prefix,ipv6",
+            "dev,my-project,my-instance,home,32,false",
+            "staging,my-project,my-instance,home,32,false",
+        ]
+    );
+
+    let output = env
+        .run(&["network", "targets", "rm", "dev", "missing"])
+        .await;
+    assert_eq!(output.status.code(), Some(10));
+
+    let output = env.run(&["network", "targets", "rm", "dev"]).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    let store: Value =
+        serde_json::from_str(&std::fs::read_to_string(env.store_path()).unwrap()).unwrap();
+    assert_eq!(
+        store["network"]["targets"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>(),
+        ["staging"]
+    );
+}
+
+async fn mock_other_instance(env: &TestEnv, network_value: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, OTHER_INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "project": PROJECT,
+            "name": OTHER_INSTANCE,
+            "settings": {
+                "ipConfiguration": {
+                    "authorizedNetworks": [
+                        { "name": "home", "value": network_value, "kind": "sql#aclEntry" },
+                    ],
+                },
+            },
+        })))
+        .mount(&env.server)
+        .await;
+}
+
+#[tokio::test]
+async fn update_all_saved_updates_stale_targets_and_skips_current() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.7/32", 1).await;
+    mock_other_instance(&env, "203.0.113.7/32").await;
+    env.save_target("dev", &["--prefix", "32"]).await;
+    let output = env
+        .run(&[
+            "network",
+            "save",
+            "analytics",
+            "-p",
+            PROJECT,
+            "-i",
+            OTHER_INSTANCE,
+            "-n",
+            "home",
+            "--prefix",
+            "32",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let output = env.run(&["network", "update", "--all-saved"]).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let status = |alias: &str| {
+        stdout
+            .lines()
+            .find(|line| line.starts_with(alias))
+            .unwrap()
+            .split_whitespace()
+            .nth(5)
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(status("dev"), "updated");
+    assert_eq!(status("analytics"), "skipped");
+}
+
+#[tokio::test]
+async fn update_all_saved_reports_failed_targets() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.7/32", 1).await;
+    env.save_target("dev", &["--prefix", "32"]).await;
+    let output = env
+        .run(&[
+            "network", "save", "office", "-p", PROJECT, "-i", INSTANCE, "-n", "office",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    // The office network disappears after it was saved
+    let store_path = env.store_path();
+    let store = std::fs::read_to_string(&store_path)
+        .unwrap()
+        .replace("\"network_name\": \"office\"", "\"network_name\": \"gone\"");
+    std::fs::write(&store_path, store).unwrap();
+
+    let output = env.run(&["network", "update", "--all-saved"]).await;
+    assert_eq!(output.status.code(), Some(13));
+    assert!(stderr_and_stdout(&output).contains("1 of 2 saved targets failed"));
+}
+
+#[tokio::test]
+async fn update_all_saved_fails_without_targets() {
+    let env = TestEnv::new().await;
+
+    let output = env.run(&["network", "update", "--all-saved"]).await;
+    assert_eq!(output.status.code(), Some(10));
+}
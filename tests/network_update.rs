@@ -0,0 +1,459 @@
+mod common;
+
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use common::{stderr_and_stdout, TestEnv, ETAG, INSTANCE, PROJECT};
+
+#[tokio::test]
+async fn update_sets_network_to_current_ip_and_saves_operation() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.0/24", 1).await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let store: Value =
+        serde_json::from_str(&std::fs::read_to_string(env.store_path()).unwrap()).unwrap();
+    assert_eq!(
+        store["network"]["last_operation"],
+        json!({
+            "project_id": PROJECT,
+            "instance_id": INSTANCE,
+            "network_name": "home",
+            "prefix": 24,
+            "ipv6": false,
+        })
+    );
+}
+
+#[tokio::test]
+async fn update_repeats_last_operation() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.0/24", 2).await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let output = env.run(&["network", "update", "--repeat-last"]).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}
+
+#[tokio::test]
+async fn update_skips_network_already_set_to_current_ip() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("203.0.113.0/24").await;
+    env.mock_update("203.0.113.0/24", 0).await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(stderr_and_stdout(&output).contains("already set to your current IP"));
+    assert!(!env.store_path().exists());
+}
+
+#[tokio::test]
+async fn update_uses_ip_override_and_prefix() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("198.18.0.42/32", 1).await;
+
+    let output = env
+        .run(&[
+            "network",
+            "update",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-n",
+            "home",
+            "--ip",
+            "198.18.0.42",
+            "--prefix",
+            "32",
+            "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}
+
+#[tokio::test]
+async fn update_rejects_ipv4_override_with_ipv6() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("198.18.0.42/32", 0).await;
+
+    let output = env
+        .run(&[
+            "network",
+            "update",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-n",
+            "home",
+            "--ip",
+            "198.18.0.42",
+            "-6",
+            "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+    assert!(stderr_and_stdout(&output).contains("IPv4 address"));
+}
+
+#[tokio::test]
+async fn update_fails_with_api_error_exit_code() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "error": {
+                "code": 403,
+                "message": "The client is not authorized to make this request.",
+                "status": "PERMISSION_DENIED",
+            },
+        })))
+        .mount(&env.server)
+        .await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(6));
+    assert!(stderr_and_stdout(&output).contains("cloudsql.instances.update"));
+}
+
+#[tokio::test]
+async fn update_fails_for_unknown_network() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.0/24", 0).await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "missing", "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(10));
+}
+
+#[tokio::test]
+async fn api_endpoint_argument_overrides_environment() {
+    let env = TestEnv::new().await;
+    let other_server = MockServer::start().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_update("203.0.113.0/24", 0).await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "error": {
+                "code": 404,
+                "message": "The Cloud SQL instance does not exist.",
+                "status": "NOT_FOUND",
+            },
+        })))
+        .expect(1)
+        .mount(&other_server)
+        .await;
+
+    let sqladmin_endpoint = format!("sqladmin={}/v1", other_server.uri());
+    let output = env
+        .run(&[
+            "network",
+            "update",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-n",
+            "home",
+            "--api-endpoint",
+            &sqladmin_endpoint,
+            "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(6));
+    assert!(stderr_and_stdout(&output).contains("does not exist"));
+}
+
+#[tokio::test]
+async fn update_reports_operation_error() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/operations/operation-1",
+            PROJECT
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "operation-1",
+            "status": "DONE",
+            "operationType": "UPDATE",
+            "error": {
+                "errors": [
+                    { "code": "INVALID_REQUEST", "message": "Invalid authorized network." },
+                ],
+            },
+        })))
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+    env.mock_update("203.0.113.0/24", 1).await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(13));
+    assert!(stderr_and_stdout(&output).contains("Invalid authorized network. (INVALID_REQUEST)"));
+    assert!(!env.store_path().exists());
+}
+
+#[tokio::test]
+async fn update_times_out_waiting_for_operation() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/operations/operation-1",
+            PROJECT
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "operation-1",
+            "status": "RUNNING",
+            "operationType": "UPDATE",
+            "startTime": "2026-01-01T00:00:00Z",
+        })))
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+    env.mock_update("203.0.113.0/24", 1).await;
+
+    let output = env
+        .run(&[
+            "network",
+            "update",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-n",
+            "home",
+            "--timeout",
+            "1",
+            "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[tokio::test]
+async fn update_without_waiting_prints_operation_id() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.0/24", 1).await;
+
+    let output = env
+        .run(&[
+            "network",
+            "update",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-n",
+            "home",
+            "--no-wait",
+            "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(stderr_and_stdout(&output).contains("operation-1"));
+    assert!(env.store_path().exists());
+}
+
+#[tokio::test]
+async fn update_reapplies_change_after_concurrent_modification() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("PATCH"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(412).set_body_json(json!({
+            "error": {
+                "code": 412,
+                "message": "Condition not met.",
+                "errors": [{ "reason": "staleData", "message": "Condition not met." }],
+            },
+        })))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&env.server)
+        .await;
+    env.mock_update("203.0.113.0/24", 1).await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(stderr_and_stdout(&output).contains("modified concurrently"));
+}
+
+#[tokio::test]
+async fn update_gives_up_after_repeated_concurrent_modifications() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("PATCH"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(412).set_body_json(json!({
+            "error": {
+                "code": 412,
+                "message": "Condition not met.",
+                "errors": [{ "reason": "staleData", "message": "Condition not met." }],
+            },
+        })))
+        .expect(3)
+        .mount(&env.server)
+        .await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[tokio::test]
+async fn update_sends_only_authorized_networks_and_keeps_unknown_fields() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "project": PROJECT,
+            "name": INSTANCE,
+            "etag": ETAG,
+            "databaseVersion": "POSTGRES_16",
+            "settings": {
+                "settingsVersion": "42",
+                "tier": "db-f1-micro",
+                "ipConfiguration": {
+                    "ipv4Enabled": true,
+                    "requireSsl": true,
+                    "allocatedIpRange": "google-managed-services",
+                    "authorizedNetworks": [
+                        { "name": "home", "value": "192.0.2.0/24", "kind": "sql#aclEntry", "futureField": 1 },
+                    ],
+                },
+            },
+        })))
+        .mount(&env.server)
+        .await;
+    Mock::given(method("PATCH"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/instances/{}",
+            PROJECT, INSTANCE
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "operation-1",
+            "status": "DONE",
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/operations/operation-1",
+            PROJECT
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "operation-1",
+            "status": "DONE",
+        })))
+        .mount(&env.server)
+        .await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+
+    let requests = env.server.received_requests().await.unwrap();
+    let patch = requests
+        .iter()
+        .find(|request| request.method.as_str() == "PATCH")
+        .unwrap();
+    let body: Value = serde_json::from_slice(&patch.body).unwrap();
+    assert_eq!(
+        body,
+        json!({
+            "etag": ETAG,
+            "settings": {
+                "settingsVersion": "42",
+                "ipConfiguration": {
+                    "authorizedNetworks": [
+                        {
+                            "name": "home",
+                            "value": "203.0.113.0/24",
+                            "kind": "sql#aclEntry",
+                            "expirationTime": null,
+                            "futureField": 1,
+                        },
+                    ],
+                },
+            },
+        })
+    );
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use std::{fs::File, path::PathBuf, time::Duration};
+
+use serde_json::Value;
+use tokio::time::{sleep, Instant};
+
+use common::{stderr_and_stdout, TestEnv, INSTANCE, PROJECT};
+
+fn lock_path(env: &TestEnv) -> PathBuf {
+    return env.store_path().with_file_name("watch.pid");
+}
+
+fn assert_lock_released(env: &TestEnv) {
+    let lock = File::open(lock_path(env)).unwrap();
+    lock.try_lock().unwrap();
+    assert_eq!(std::fs::read_to_string(lock_path(env)).unwrap(), "");
+}
+
+#[tokio::test]
+async fn watch_once_syncs_saved_targets_and_releases_lock() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.0/24", 1).await;
+    env.save_target("dev", &[]).await;
+
+    let output = env.run(&["network", "watch", "--once"]).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(stderr_and_stdout(&output).contains("dev: updated home"));
+    assert_lock_released(&env);
+}
+
+#[tokio::test]
+async fn watch_syncs_ipv6_targets_without_ipv6_flag() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("2001:db8:1:2::7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("2001:db8:1:2::/64", 1).await;
+    env.save_target("dev", &["-6"]).await;
+
+    let output = env.run(&["network", "watch", "--once"]).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(stderr_and_stdout(&output).contains("Current IP is 2001:db8:1:2::7"));
+}
+
+/// Values the PATCH requests received so far set `network` to
+async fn updated_values(env: &TestEnv, network: &str) -> Vec<Value> {
+    return env
+        .update_bodies()
+        .await
+        .iter()
+        .map(|body| {
+            body["settings"]["ipConfiguration"]["authorizedNetworks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|item| item["name"] == network)
+                .unwrap()["value"]
+                .clone()
+        })
+        .collect();
+}
+
+#[tokio::test]
+async fn watch_syncs_targets_saved_while_running() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_any_update().await;
+    env.save_target("dev", &[]).await;
+
+    let mut watcher = env
+        .command(&["network", "watch", "--interval", "1"])
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while env.update_bodies().await.is_empty() && Instant::now() < deadline {
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(updated_values(&env, "home").await, ["203.0.113.0/24"]);
+
+    // The IP didn't change, only the new target is synced
+    let output = env
+        .run(&[
+            "network", "save", "office", "-p", PROJECT, "-i", INSTANCE, "-n", "office",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    while env.update_bodies().await.len() < 2 && Instant::now() < deadline {
+        sleep(Duration::from_millis(100)).await;
+    }
+    // Give the watcher another check to make sure synced targets aren't updated again
+    sleep(Duration::from_millis(1500)).await;
+    watcher.kill().await.unwrap();
+
+    assert_eq!(
+        updated_values(&env, "office").await,
+        ["198.51.100.0/24", "203.0.113.0/24"]
+    );
+    assert_eq!(
+        updated_values(&env, "home").await,
+        ["203.0.113.0/24", "192.0.2.0/24"]
+    );
+}
+
+#[tokio::test]
+async fn watch_refuses_to_run_alongside_another_watcher() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.save_target("dev", &[]).await;
+    let lock = File::create(lock_path(&env)).unwrap();
+    lock.try_lock().unwrap();
+    std::fs::write(lock_path(&env), "4242").unwrap();
+
+    let output = env.run(&["network", "watch", "--once"]).await;
+    assert_eq!(output.status.code(), Some(14));
+    assert!(stderr_and_stdout(&output).contains("already running with PID 4242"));
+    assert_eq!(std::fs::read_to_string(lock_path(&env)).unwrap(), "4242");
+}
+
+#[tokio::test]
+async fn watch_ignores_unlocked_lock_file() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("203.0.113.0/24").await;
+    env.mock_update("203.0.113.0/24", 0).await;
+    env.save_target("dev", &[]).await;
+    // Left behind by a watcher that was killed, the OS released its lock
+    std::fs::write(lock_path(&env), "4294967295").unwrap();
+
+    let output = env.run(&["network", "watch", "--once"]).await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(stderr_and_stdout(&output).contains("dev: skipped"));
+    assert_lock_released(&env);
+}
+
+#[tokio::test]
+async fn watch_fails_for_unknown_target() {
+    let env = TestEnv::new().await;
+
+    let output = env
+        .run(&["network", "watch", "--once", "--target", "missing"])
+        .await;
+    assert_eq!(output.status.code(), Some(10));
+}
+
+#[tokio::test]
+async fn watch_prints_systemd_unit() {
+    let env = TestEnv::new().await;
+
+    let output = env
+        .run(&[
+            "network",
+            "watch",
+            "--systemd-unit",
+            "--target",
+            "dev",
+            "--interval",
+            "60",
+            "--page-size",
+            "50",
+            "--project-filter",
+            "parent.id:123",
+            "--max-attempts",
+            "2",
+            "--api-endpoint",
+            "ip=http://localhost/ip",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let exec_start = stdout
+        .lines()
+        .find(|line| line.starts_with("ExecStart="))
+        .unwrap();
+    assert!(exec_start.ends_with(
+        " network watch --target dev --interval 60 --page-size 50 --project-filter \
+         parent.id:123 --max-attempts 2 --api-endpoint ip=http://localhost/ip"
+    ));
+    assert!(stdout.contains("WantedBy=default.target"));
+    // User units can't depend on system targets like network-online.target
+    assert!(!stdout.contains("network-online.target"));
+}
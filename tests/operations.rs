@@ -0,0 +1,139 @@
+mod common;
+
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path, query_param},
+    Mock, ResponseTemplate,
+};
+
+use common::{stderr_and_stdout, TestEnv, INSTANCE, PROJECT};
+
+fn operation(name: &str, status: &str) -> Value {
+    return json!({
+        "name": name,
+        "status": status,
+        "operationType": "UPDATE",
+        "user": "someone@example.com",
+        "targetId": INSTANCE,
+        "targetProject": PROJECT,
+        "insertTime": "2026-01-01T00:00:00Z",
+        "startTime": "2026-01-01T00:00:01Z",
+    });
+}
+
+async fn mock_operation(env: &TestEnv, name: &str, status: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/operations/{}",
+            PROJECT, name
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(operation(name, status)))
+        .mount(&env.server)
+        .await;
+}
+
+#[tokio::test]
+async fn list_prints_recent_operations() {
+    let env = TestEnv::new().await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("GET"))
+        .and(path(format!("/sql/v1/projects/{}/operations", PROJECT)))
+        .and(query_param("instance", INSTANCE))
+        .and(query_param("maxResults", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [operation("operation-2", "RUNNING"), operation("operation-1", "DONE")],
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let output = env
+        .run(&[
+            "operations",
+            "list",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "--limit",
+            "2",
+            "-o",
+            "csv",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "id,instance,type,status,user,start_time,end_time,error",
+            "operation-2,my-instance,UPDATE,RUNNING,someone@example.com,2026-01-01T00:00:01Z,,",
+            "operation-1,my-instance,UPDATE,DONE,someone@example.com,2026-01-01T00:00:01Z,,",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn describe_prints_operation_as_json() {
+    let env = TestEnv::new().await;
+    mock_operation(&env, "operation-1", "DONE").await;
+
+    let output = env
+        .run(&[
+            "operations",
+            "describe",
+            "operation-1",
+            "-p",
+            PROJECT,
+            "-o",
+            "json",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    let operation: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(operation["name"], "operation-1");
+    assert_eq!(operation["user"], "someone@example.com");
+}
+
+#[tokio::test]
+async fn wait_returns_once_operation_is_done() {
+    let env = TestEnv::new().await;
+    mock_operation(&env, "operation-1", "DONE").await;
+
+    let output = env
+        .run(&["operations", "wait", "operation-1", "-p", PROJECT])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}
+
+#[tokio::test]
+async fn cancel_cancels_running_operation() {
+    let env = TestEnv::new().await;
+    mock_operation(&env, "operation-1", "RUNNING").await;
+    Mock::given(method("POST"))
+        .and(path(format!(
+            "/sql/v1/projects/{}/operations/operation-1/cancel",
+            PROJECT
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let output = env
+        .run(&["operations", "cancel", "operation-1", "-p", PROJECT, "-y"])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}
+
+#[tokio::test]
+async fn cancel_rejects_finished_operation() {
+    let env = TestEnv::new().await;
+    mock_operation(&env, "operation-1", "DONE").await;
+
+    let output = env
+        .run(&["operations", "cancel", "operation-1", "-p", PROJECT, "-y"])
+        .await;
+    assert_eq!(output.status.code(), Some(11));
+}
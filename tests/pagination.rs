@@ -0,0 +1,106 @@
+mod common;
+
+use gsqueal::client::{ResourceManagerClient, SqlAdminClient};
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path, query_param, query_param_is_missing},
+    Mock, ResponseTemplate,
+};
+
+use common::{TestEnv, PROJECT};
+
+#[tokio::test]
+async fn fetch_projects_follows_next_page_token() {
+    let env = TestEnv::new().await;
+    Mock::given(method("GET"))
+        .and(path("/crm/v1/projects"))
+        .and(query_param("filter", "lifecycleState:ACTIVE"))
+        .and(query_param("pageSize", "2"))
+        .and(query_param_is_missing("pageToken"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "projects": [
+                { "projectId": "project-a", "name": "Project A" },
+                { "projectId": "project-b", "name": "Project B" },
+            ],
+            "nextPageToken": "page-2",
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/crm/v1/projects"))
+        .and(query_param("filter", "lifecycleState:ACTIVE"))
+        .and(query_param("pageSize", "2"))
+        .and(query_param("pageToken", "page-2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "projects": [{ "projectId": "project-c", "name": "Project C" }],
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let resource_manager = ResourceManagerClient::new(env.api_client())
+        .with_base_url(format!("{}/crm/v1", env.server.uri()));
+    let mut counts = Vec::new();
+    let projects = resource_manager
+        .fetch_projects("lifecycleState:ACTIVE", Some(2), |count| counts.push(count))
+        .await
+        .unwrap();
+
+    let project_ids: Vec<_> = projects
+        .iter()
+        .map(|project| project.project_id.as_str())
+        .collect();
+    assert_eq!(project_ids, ["project-a", "project-b", "project-c"]);
+    assert_eq!(counts, [2, 3]);
+}
+
+fn instance(name: &str) -> serde_json::Value {
+    return json!({
+        "project": PROJECT,
+        "name": name,
+        "settings": { "ipConfiguration": { "authorizedNetworks": [] } },
+    });
+}
+
+#[tokio::test]
+async fn fetch_instances_follows_next_page_token() {
+    let env = TestEnv::new().await;
+    let instances_path = format!("/sql/v1/projects/{}/instances", PROJECT);
+    Mock::given(method("GET"))
+        .and(path(instances_path.as_str()))
+        .and(query_param("maxResults", "1"))
+        .and(query_param_is_missing("pageToken"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [instance("instance-a")],
+            "nextPageToken": "page-2",
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(instances_path.as_str()))
+        .and(query_param("maxResults", "1"))
+        .and(query_param("pageToken", "page-2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "items": [instance("instance-b")],
+            // An empty token ends the listing like a missing one
+            "nextPageToken": "",
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let sql_admin =
+        SqlAdminClient::new(env.api_client()).with_base_url(format!("{}/sql/v1", env.server.uri()));
+    let instances = sql_admin
+        .fetch_instances(PROJECT, Some(1), |_| {})
+        .await
+        .unwrap();
+
+    let names: Vec<_> = instances
+        .iter()
+        .map(|instance| instance.name.as_str())
+        .collect();
+    assert_eq!(names, ["instance-a", "instance-b"]);
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use common::{TestEnv, INSTANCE};
+
+/// Lists networks without `--project` so the project is discovered, which finds none
+async fn list_without_project(env: &TestEnv, extra_args: &[&str]) {
+    let mut args = vec!["network", "list", "-i", INSTANCE, "--ip", "192.0.2.7"];
+    args.extend_from_slice(extra_args);
+    env.run(&args).await;
+}
+
+async fn mock_no_projects(env: &TestEnv) {
+    Mock::given(method("GET"))
+        .and(path("/crm/v1/projects"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&env.server)
+        .await;
+}
+
+/// `filter` query parameters of the project discovery requests received so far
+async fn project_filters(env: &TestEnv) -> Vec<String> {
+    return env
+        .server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == "/crm/v1/projects")
+        .map(|request| {
+            request
+                .url
+                .query_pairs()
+                .find(|(name, _)| name == "filter")
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default()
+        })
+        .collect();
+}
+
+#[tokio::test]
+async fn discovery_uses_active_projects_filter_by_default() {
+    let env = TestEnv::new().await;
+    mock_no_projects(&env).await;
+
+    list_without_project(&env, &[]).await;
+    assert_eq!(project_filters(&env).await, ["lifecycleState:ACTIVE"]);
+}
+
+#[tokio::test]
+async fn discovery_uses_configured_filter() {
+    let env = TestEnv::new().await;
+    env.write_config(json!({ "projects": { "filter": "labels.team:data" } }));
+    mock_no_projects(&env).await;
+
+    list_without_project(&env, &[]).await;
+    assert_eq!(project_filters(&env).await, ["labels.team:data"]);
+}
+
+#[tokio::test]
+async fn project_filter_argument_overrides_config() {
+    let env = TestEnv::new().await;
+    env.write_config(json!({
+        "projects": { "filter": "labels.team:data", "pinned": ["pinned-project"] },
+    }));
+    mock_no_projects(&env).await;
+
+    list_without_project(&env, &["--project-filter", "parent.id:123456789"]).await;
+    assert_eq!(project_filters(&env).await, ["parent.id:123456789"]);
+}
+
+#[tokio::test]
+async fn pinned_projects_skip_discovery() {
+    let env = TestEnv::new().await;
+    env.write_config(json!({ "projects": { "pinned": ["pinned-project"] } }));
+    mock_no_projects(&env).await;
+
+    // Selecting among the pinned projects needs a terminal, only discovery matters here
+    list_without_project(&env, &[]).await;
+    assert_eq!(project_filters(&env).await, Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn discovery_without_projects_fails_with_not_found() {
+    let env = TestEnv::new().await;
+    mock_no_projects(&env).await;
+
+    let output = env
+        .run(&["network", "list", "-i", INSTANCE, "--ip", "192.0.2.7"])
+        .await;
+    assert_eq!(output.status.code(), Some(10));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Cannot find any projects"));
+}
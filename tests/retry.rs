@@ -0,0 +1,131 @@
+mod common;
+
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use common::{stderr_and_stdout, TestEnv, INSTANCE, PROJECT};
+
+fn instance_path() -> String {
+    return format!("/sql/v1/projects/{}/instances/{}", PROJECT, INSTANCE);
+}
+
+fn unavailable() -> ResponseTemplate {
+    return ResponseTemplate::new(503)
+        .insert_header("Retry-After", "0")
+        .set_body_json(json!({
+            "error": {
+                "code": 503,
+                "message": "The service is currently unavailable.",
+                "status": "UNAVAILABLE",
+            },
+        }));
+}
+
+#[tokio::test]
+async fn get_is_retried_after_transient_failure() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    Mock::given(method("GET"))
+        .and(path(instance_path()))
+        .respond_with(unavailable())
+        .up_to_n_times(2)
+        .with_priority(1)
+        .expect(2)
+        .mount(&env.server)
+        .await;
+    env.mock_instance("192.0.2.0/24").await;
+    env.mock_update("203.0.113.0/24", 1).await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+    assert!(stderr_and_stdout(&output).contains("retrying"));
+}
+
+#[tokio::test]
+async fn get_gives_up_after_max_attempts() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    Mock::given(method("GET"))
+        .and(path(instance_path()))
+        .respond_with(unavailable())
+        .expect(3)
+        .mount(&env.server)
+        .await;
+
+    let output = env
+        .run(&[
+            "network",
+            "update",
+            "-p",
+            PROJECT,
+            "-i",
+            INSTANCE,
+            "-n",
+            "home",
+            "--max-attempts",
+            "3",
+            "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[tokio::test]
+async fn patch_is_not_retried_after_server_error() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("PATCH"))
+        .and(path(instance_path()))
+        .respond_with(unavailable())
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[tokio::test]
+async fn patch_is_retried_when_rate_limited() {
+    let env = TestEnv::new().await;
+    env.mock_current_ip("203.0.113.7").await;
+    env.mock_instance("192.0.2.0/24").await;
+    Mock::given(method("PATCH"))
+        .and(path(instance_path()))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("Retry-After", "0")
+                .set_body_json(json!({
+                    "error": {
+                        "code": 429,
+                        "message": "Quota exceeded.",
+                        "status": "RESOURCE_EXHAUSTED",
+                    },
+                })),
+        )
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&env.server)
+        .await;
+    env.mock_update("203.0.113.0/24", 1).await;
+
+    let output = env
+        .run(&[
+            "network", "update", "-p", PROJECT, "-i", INSTANCE, "-n", "home", "-y",
+        ])
+        .await;
+    assert!(output.status.success(), "{}", stderr_and_stdout(&output));
+}